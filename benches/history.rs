@@ -0,0 +1,72 @@
+//! Walks a generated history of 50 000 commits, or `GIT_STATS_BENCH_COMMITS`.
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+use chrono::{DateTime, Duration, SecondsFormat};
+use criterion::{Criterion, criterion_group, criterion_main};
+use git_stats::{
+    StatsQuery,
+    users::{UserInfo, get_users, update_user_stats},
+};
+use support::{ALICE, Author, BOB, CAROL, DAN, ERIN, FRANK, Fixture};
+
+const AUTHORS: [Author; 6] = [ALICE, BOB, CAROL, DAN, ERIN, FRANK];
+
+const FILES: usize = 40;
+
+/// A linear history where every commit appends a line to one of `FILES`
+/// files, one commit an hour.
+fn generate(commits: usize) -> Fixture {
+    let fixture = Fixture::new();
+    let start = DateTime::parse_from_rfc3339("2015-01-01T09:00:00+01:00").unwrap();
+    let mut contents = vec![String::new(); FILES];
+    for i in 0..commits {
+        let file = i * 7 % FILES;
+        contents[file].push_str(&format!("line {}\n", i));
+        let time = start + Duration::hours(i as i64);
+        fixture
+            .commit(
+                AUTHORS[i % AUTHORS.len()],
+                &time.to_rfc3339_opts(SecondsFormat::Secs, false),
+            )
+            .write(
+                &format!("src/dir_{}/file_{}.txt", file % 4, file),
+                &contents[file],
+            )
+            .create();
+    }
+    fixture
+}
+
+fn history(c: &mut Criterion) {
+    let commits = std::env::var("GIT_STATS_BENCH_COMMITS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(50_000);
+    let fixture = generate(commits);
+
+    let mut group = c.benchmark_group(format!("history of {} commits", commits));
+    group.sample_size(10);
+    group.bench_function("StatsQuery::run", |b| {
+        b.iter(|| StatsQuery::new(&fixture.repo).run().unwrap())
+    });
+    group.bench_function("get_users", |b| {
+        b.iter(|| get_users(&fixture.repo).unwrap())
+    });
+    group.bench_function("update_user_stats", |b| {
+        let users: Vec<UserInfo> = AUTHORS
+            .iter()
+            .map(|a| UserInfo::new(a.name.to_string(), a.email.to_string()))
+            .collect();
+        b.iter(|| {
+            let mut users = users.clone();
+            update_user_stats(&mut users, &fixture.repo, "HEAD").unwrap();
+            users
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, history);
+criterion_main!(benches);
@@ -0,0 +1,384 @@
+use std::path::{Path, PathBuf};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver, Sender, TryRecvError},
+};
+use std::thread;
+
+use chrono::prelude::*;
+use git2::{Commit, Oid, Repository, Signature};
+
+use crate::churn::{Churn, ChurnOptions};
+use crate::error::{Result, StatsError};
+use crate::query::StatsQuery;
+use crate::repository::{self, ShallowBoundary};
+use crate::submodules;
+use crate::users::{FileChanges, get_file_changes, utc_from_commit};
+
+/// A single analyzed commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitRecord {
+    pub oid: Oid,
+    pub author_name: String,
+    pub author_email: String,
+    pub time: DateTime<Utc>,
+    pub summary: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub files: Vec<FileChange>,
+    /// Changes left out of the line counts, to binary, generated and vendored
+    /// files. See [`ChurnOptions`].
+    pub excluded_files: Vec<FileChange>,
+    /// Files renamed or copied by the commit, listed under their new path.
+    pub renames: usize,
+    pub copies: usize,
+    /// Name of the history the commit comes from, e.g. a submodule, or `None`
+    /// for the analyzed repository. See
+    /// [`HistorySource::name`](crate::users::HistorySource::name).
+    pub source: Option<String>,
+}
+
+/// Lines changed in a single file by a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl CommitRecord {
+    /// A record of `commit`, with the default [`ChurnOptions`].
+    pub fn from_commit(repo: &Repository, commit: &Commit) -> Result<Self> {
+        let churn = Churn::new(repo, &ChurnOptions::default())?;
+        let changes = get_file_changes(repo, commit, &[], &churn)?;
+        CommitRecord::new(commit, &commit.author(), changes)
+    }
+
+    /// A record of `commit` by `author`, which may differ from the one of the
+    /// commit once resolved with a mailmap.
+    pub(crate) fn new(commit: &Commit, author: &Signature, changes: FileChanges) -> Result<Self> {
+        let files = changes.counted;
+        Ok(CommitRecord {
+            oid: commit.id(),
+            author_name: author.name().unwrap_or("Unknown").to_string(),
+            author_email: author.email().unwrap_or("Unknown").to_string(),
+            time: utc_from_commit(commit)?,
+            summary: commit.summary().unwrap_or_default().to_string(),
+            lines_added: files.iter().map(|f| f.lines_added).sum(),
+            lines_removed: files.iter().map(|f| f.lines_removed).sum(),
+            files,
+            excluded_files: changes.excluded,
+            renames: changes.renames,
+            copies: changes.copies,
+            source: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// Percentage of the commits processed so far, between 0 and 100.
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            self.processed as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AnalysisEvent {
+    /// The first pass counted the commits that are going to be analyzed.
+    Started {
+        total_commits: usize,
+        /// Commit the revspec resolved to.
+        tip: Oid,
+        /// Set when the repository is a shallow clone.
+        partial: Option<ShallowBoundary>,
+    },
+    Commit(CommitRecord),
+    /// Sent every time the completed percentage changes.
+    Progress(Progress),
+    Finished,
+    Cancelled,
+    Failed(StatsError),
+}
+
+impl PartialEq for AnalysisEvent {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                AnalysisEvent::Started {
+                    total_commits,
+                    tip,
+                    partial,
+                },
+                AnalysisEvent::Started {
+                    total_commits: other_total_commits,
+                    tip: other_tip,
+                    partial: other_partial,
+                },
+            ) => {
+                total_commits == other_total_commits && tip == other_tip && partial == other_partial
+            }
+            (AnalysisEvent::Commit(record), AnalysisEvent::Commit(other)) => record == other,
+            (AnalysisEvent::Progress(progress), AnalysisEvent::Progress(other)) => {
+                progress == other
+            }
+            (AnalysisEvent::Finished, AnalysisEvent::Finished)
+            | (AnalysisEvent::Cancelled, AnalysisEvent::Cancelled) => true,
+            // Errors don't implement `PartialEq`, their messages are compared
+            (AnalysisEvent::Failed(err), AnalysisEvent::Failed(other)) => {
+                err.to_string() == other.to_string()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl AnalysisEvent {
+    /// Whether no more events will follow this one.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            AnalysisEvent::Finished | AnalysisEvent::Cancelled | AnalysisEvent::Failed(_)
+        )
+    }
+}
+
+/// A repository analysis running on a background thread.
+///
+/// Events can be polled without blocking with [`Analysis::try_next`], e.g. from
+/// a UI tick, or consumed by iterating, which blocks until the next event. The
+/// analysis is cancelled when the handle is dropped.
+pub struct Analysis {
+    repo_path: PathBuf,
+    revspec: String,
+    churn: ChurnOptions,
+    recurse_submodules: bool,
+    receiver: Receiver<AnalysisEvent>,
+    cancelled: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl Analysis {
+    /// Starts analyzing the history reachable from `revspec` in the repository
+    /// at `repo_path`.
+    pub fn spawn(repo_path: impl Into<PathBuf>, revspec: impl Into<String>) -> Self {
+        Analysis::spawn_since(repo_path, revspec, ChurnOptions::default(), None)
+    }
+
+    /// Like [`Analysis::spawn`], counting the lines changed by each commit
+    /// with `churn`.
+    pub fn spawn_with(
+        repo_path: impl Into<PathBuf>,
+        revspec: impl Into<String>,
+        churn: ChurnOptions,
+    ) -> Self {
+        Analysis::spawn_since(repo_path, revspec, churn, None)
+    }
+
+    /// Like [`Analysis::spawn_with`], but leaves out the commits reachable from
+    /// `since`, e.g. the ones a previous analysis already went through.
+    pub fn spawn_since(
+        repo_path: impl Into<PathBuf>,
+        revspec: impl Into<String>,
+        churn: ChurnOptions,
+        since: Option<Oid>,
+    ) -> Self {
+        Analysis::spawn_worker(repo_path.into(), revspec.into(), churn, since, false)
+    }
+
+    /// Like [`Analysis::spawn_with`], also analyzing the history of every
+    /// submodule up to its pinned commit. The commits of a submodule are
+    /// tagged with its name, see [`CommitRecord::source`].
+    pub fn spawn_recursive(
+        repo_path: impl Into<PathBuf>,
+        revspec: impl Into<String>,
+        churn: ChurnOptions,
+    ) -> Self {
+        Analysis::spawn_worker(repo_path.into(), revspec.into(), churn, None, true)
+    }
+
+    fn spawn_worker(
+        repo_path: PathBuf,
+        revspec: String,
+        churn: ChurnOptions,
+        since: Option<Oid>,
+        recurse_submodules: bool,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let worker_cancelled = cancelled.clone();
+        let worker_path = repo_path.clone();
+        let worker_revspec = revspec.clone();
+        let worker_churn = churn.clone();
+        thread::spawn(move || {
+            let result = analyze(
+                &worker_path,
+                &worker_revspec,
+                worker_churn,
+                since,
+                recurse_submodules,
+                &sender,
+                &worker_cancelled,
+            );
+            let event = match result {
+                Ok(()) if worker_cancelled.load(Ordering::Relaxed) => AnalysisEvent::Cancelled,
+                Ok(()) => AnalysisEvent::Finished,
+                Err(err) => AnalysisEvent::Failed(err),
+            };
+            let _ = sender.send(event);
+        });
+
+        Analysis {
+            repo_path,
+            revspec,
+            churn,
+            recurse_submodules,
+            receiver,
+            cancelled,
+            done: false,
+        }
+    }
+
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    pub fn revspec(&self) -> &str {
+        &self.revspec
+    }
+
+    pub fn churn(&self) -> &ChurnOptions {
+        &self.churn
+    }
+
+    /// Whether the submodules are analyzed too, see [`Analysis::spawn_recursive`].
+    pub fn recurses_submodules(&self) -> bool {
+        self.recurse_submodules
+    }
+
+    /// Asks the worker to stop. A [`AnalysisEvent::Cancelled`] event is sent
+    /// once it does.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the next event if one is ready, without blocking.
+    pub fn try_next(&mut self) -> Option<AnalysisEvent> {
+        if self.done {
+            return None;
+        }
+        match self.receiver.try_recv() {
+            Ok(event) => {
+                self.done = event.is_terminal();
+                Some(event)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.done = true;
+                None
+            }
+        }
+    }
+}
+
+impl Iterator for Analysis {
+    type Item = AnalysisEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.receiver.recv().ok();
+        self.done = event.as_ref().is_none_or(AnalysisEvent::is_terminal);
+        event
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+fn analyze(
+    repo_path: &Path,
+    revspec: &str,
+    churn: ChurnOptions,
+    since: Option<Oid>,
+    recurse_submodules: bool,
+    sender: &Sender<AnalysisEvent>,
+    cancelled: &AtomicBool,
+) -> Result<()> {
+    let repo = repository::open(repo_path)?;
+    let mut query = StatsQuery::new(&repo).revspec(revspec).churn(churn);
+    let submodules = if recurse_submodules {
+        submodules::open_submodules(&repo, query.tip()?)?
+    } else {
+        Default::default()
+    };
+    if let Some(since) = since {
+        query = query.hide(since);
+    }
+    for submodule in &submodules.repos {
+        query = query.include(submodule.history());
+    }
+    let start = query.tip()?;
+    let partial = query.shallow_boundary()?;
+
+    // First pass only counts the commits, so progress can be reported
+    let mut total = 0;
+    for oid in query.revwalks()?.into_iter().flatten() {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        oid?;
+        total += 1;
+    }
+    if sender
+        .send(AnalysisEvent::Started {
+            total_commits: total,
+            tip: start,
+            partial,
+        })
+        .is_err()
+    {
+        return Ok(());
+    }
+
+    let mut last_percent = 0;
+    for (processed, record) in (1..).zip(query.walk()?) {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(record) = record?
+            && sender.send(AnalysisEvent::Commit(record)).is_err()
+        {
+            // Nobody is listening anymore
+            return Ok(());
+        }
+
+        let progress = Progress { processed, total };
+        let percent = progress.percent() as u32;
+        if percent != last_percent {
+            last_percent = percent;
+            let _ = sender.send(AnalysisEvent::Progress(progress));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,365 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+
+use git2::{
+    AttrCheckFlags, AttrValue, Diff, DiffFindOptions, DiffOptions, Oid, Patch, Pathspec,
+    PathspecFlags, Repository,
+};
+
+use crate::error::{Result, StatsError};
+
+/// Files left out of the line counts unless [`ChurnOptions::default_excludes`]
+/// is turned off: lock files, minified code and vendored dependencies. These
+/// are git pathspecs, where `*` also matches `/`.
+pub const DEFAULT_EXCLUDES: [&str; 16] = [
+    "Cargo.lock",
+    "*/Cargo.lock",
+    "package-lock.json",
+    "*/package-lock.json",
+    "yarn.lock",
+    "*/yarn.lock",
+    "pnpm-lock.yaml",
+    "*/pnpm-lock.yaml",
+    "*.min.js",
+    "*.min.css",
+    "vendor/*",
+    "*/vendor/*",
+    "node_modules/*",
+    "*/node_modules/*",
+    "third_party/*",
+    "*/third_party/*",
+];
+
+/// Default of [`ChurnOptions::similarity_threshold`], the one of git.
+pub const DEFAULT_SIMILARITY_THRESHOLD: u16 = 50;
+
+/// libgit2's `GIT_ATTR_CHECK_INCLUDE_HEAD`, which git2 has no constant for.
+/// Reads the `.gitattributes` files of `HEAD`, so bare repositories have
+/// attributes too.
+const ATTR_CHECK_INCLUDE_HEAD: u32 = 1 << 3;
+
+/// Alphanumeric characters a block of lines needs to count as moved, the
+/// threshold of `git diff --color-moved`. Shorter blocks, e.g. a lone closing
+/// brace, are too common to tell a move from a coincidence.
+const MIN_MOVED_ALNUM: usize = 20;
+
+/// How the lines changed by a commit are counted.
+///
+/// Changes to excluded files are still reported, apart from the other ones,
+/// see [`CommitRecord::excluded_files`](crate::analysis::CommitRecord).
+/// Binary files are always excluded, they have no lines, and so are all the
+/// files changed by the [`ignored_revs`](ChurnOptions::ignored_revs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChurnOptions {
+    /// Whether the files matching [`DEFAULT_EXCLUDES`] are excluded.
+    pub default_excludes: bool,
+    /// More files to exclude, as git pathspecs such as `docs/*.svg`.
+    pub excludes: Vec<String>,
+    /// Whether files marked `linguist-generated`, `linguist-vendored` or
+    /// `-diff` in `.gitattributes` are excluded. Setting a `linguist-`
+    /// attribute to false keeps a file the default exclusions would leave out.
+    pub attributes: bool,
+    /// How similar, in percent, a file must be to a deleted or changed one to
+    /// be counted as renamed or copied from it, like `git log -M -C`. Only
+    /// the lines that differ are then counted. `None` turns the detection off,
+    /// so a renamed file counts as deleted and added again.
+    pub similarity_threshold: Option<u16>,
+    /// Whether lines that only differ in whitespace are left out, like
+    /// `git diff -w`, so reindenting code doesn't count as changing it.
+    pub ignore_whitespace: bool,
+    /// Whether blocks of lines a commit removes from a place and adds back in
+    /// another one, in the same file or in another file, are left out, like
+    /// `git diff --color-moved` shows them. Only the blocks of at least 20
+    /// alphanumeric characters count as moved. With
+    /// [`ignore_whitespace`](ChurnOptions::ignore_whitespace), reindented
+    /// blocks are moves too.
+    pub ignore_moves: bool,
+    /// Commits whose changes are all excluded, such as the ones that only
+    /// reformat the code. See [`read_ignore_revs`].
+    pub ignored_revs: Vec<Oid>,
+}
+
+impl Default for ChurnOptions {
+    fn default() -> Self {
+        ChurnOptions {
+            default_excludes: true,
+            excludes: Vec::new(),
+            attributes: true,
+            similarity_threshold: Some(DEFAULT_SIMILARITY_THRESHOLD),
+            ignore_whitespace: false,
+            ignore_moves: false,
+            ignored_revs: Vec::new(),
+        }
+    }
+}
+
+impl ChurnOptions {
+    /// Counts the lines of every text file.
+    pub fn include_all() -> Self {
+        ChurnOptions {
+            default_excludes: false,
+            excludes: Vec::new(),
+            attributes: false,
+            ..ChurnOptions::default()
+        }
+    }
+}
+
+/// Reads a list of commits to ignore in the format of git's
+/// `blame.ignoreRevsFile`, such as `.git-blame-ignore-revs`: a full commit
+/// hash per line, with `#` starting a comment.
+pub fn read_ignore_revs(path: &Path) -> Result<Vec<Oid>> {
+    let error = |source| StatsError::IgnoreRevsFile {
+        path: path.to_path_buf(),
+        source,
+    };
+    let contents = std::fs::read_to_string(path).map_err(error)?;
+    let mut revs = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let hash = line.split('#').next().unwrap_or_default().trim();
+        if hash.is_empty() {
+            continue;
+        }
+        let oid = Oid::from_str(hash)
+            .ok()
+            .filter(|_| hash.len() == 40)
+            .ok_or_else(|| {
+                error(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: '{}' is not a full commit hash", number + 1, hash),
+                ))
+            })?;
+        revs.push(oid);
+    }
+    Ok(revs)
+}
+
+/// [`ChurnOptions`] ready to be applied to the diffs of a repository.
+pub(crate) struct Churn<'r> {
+    repo: &'r Repository,
+    pathspec: Option<Pathspec>,
+    attributes: bool,
+    similarity_threshold: Option<u16>,
+    ignore_whitespace: bool,
+    ignore_moves: bool,
+    ignored_revs: HashSet<Oid>,
+    /// Whether each path seen so far is excluded, the attributes being slow to
+    /// look up.
+    cache: RefCell<HashMap<String, bool>>,
+}
+
+impl<'r> Churn<'r> {
+    pub(crate) fn new(repo: &'r Repository, options: &ChurnOptions) -> Result<Self> {
+        let mut patterns: Vec<&str> = Vec::new();
+        if options.default_excludes {
+            patterns.extend(DEFAULT_EXCLUDES);
+        }
+        patterns.extend(options.excludes.iter().map(String::as_str));
+        let pathspec = if patterns.is_empty() {
+            None
+        } else {
+            Some(Pathspec::new(patterns)?)
+        };
+        Ok(Churn {
+            repo,
+            pathspec,
+            attributes: options.attributes,
+            similarity_threshold: options.similarity_threshold,
+            ignore_whitespace: options.ignore_whitespace,
+            ignore_moves: options.ignore_moves,
+            ignored_revs: options.ignored_revs.iter().copied().collect(),
+            cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Options to diff the commits with.
+    pub(crate) fn diff_options(&self) -> DiffOptions {
+        let mut options = DiffOptions::new();
+        options.ignore_whitespace(self.ignore_whitespace);
+        options
+    }
+
+    /// Whether all the changes of the commit `oid` are left out of the counts.
+    pub(crate) fn is_ignored(&self, oid: Oid) -> bool {
+        self.ignored_revs.contains(&oid)
+    }
+
+    /// Collects the lines moved by a commit, if they are left out of the counts.
+    pub(crate) fn moved_lines(&self) -> Option<MovedLines> {
+        self.ignore_moves.then(|| MovedLines {
+            ignore_whitespace: self.ignore_whitespace,
+            ..MovedLines::default()
+        })
+    }
+
+    /// Pairs the deleted and added files of `diff` that are renames, and the
+    /// added files that are copies of changed ones.
+    pub(crate) fn find_similar(&self, diff: &mut Diff) -> Result<(), git2::Error> {
+        let Some(threshold) = self.similarity_threshold else {
+            return Ok(());
+        };
+        let mut options = DiffFindOptions::new();
+        options
+            .renames(true)
+            .copies(true)
+            .rename_threshold(threshold)
+            .copy_threshold(threshold)
+            .ignore_whitespace(self.ignore_whitespace);
+        diff.find_similar(Some(&mut options))
+    }
+
+    /// Whether the changes to the file at `path` are left out of the counts.
+    pub(crate) fn is_excluded(&self, path: &str) -> bool {
+        if let Some(excluded) = self.cache.borrow().get(path) {
+            return *excluded;
+        }
+        let excluded = self.check(path);
+        self.cache.borrow_mut().insert(path.to_string(), excluded);
+        excluded
+    }
+
+    fn check(&self, path: &str) -> bool {
+        let mut keep = false;
+        if self.attributes {
+            for name in ["linguist-generated", "linguist-vendored"] {
+                match self.attribute(path, name) {
+                    AttrValue::True => return true,
+                    AttrValue::String(value) if value.eq_ignore_ascii_case("true") => return true,
+                    AttrValue::False => keep = true,
+                    AttrValue::String(value) if value.eq_ignore_ascii_case("false") => keep = true,
+                    _ => (),
+                }
+            }
+            if self.attribute(path, "diff") == AttrValue::False {
+                return true;
+            }
+        }
+        !keep
+            && self.pathspec.as_ref().is_some_and(|pathspec| {
+                pathspec.matches_path(Path::new(path), PathspecFlags::DEFAULT)
+            })
+    }
+
+    fn attribute(&self, path: &str, name: &str) -> AttrValue<'_> {
+        let flags = AttrCheckFlags::INDEX_THEN_FILE
+            | AttrCheckFlags::from_bits_retain(ATTR_CHECK_INCLUDE_HEAD);
+        self.repo
+            .get_attr_bytes(Path::new(path), name, flags)
+            .map_or(AttrValue::Unspecified, AttrValue::from_bytes)
+    }
+}
+
+/// Blocks of lines removed from a place and added back in another one by a
+/// commit, see [`ChurnOptions::ignore_moves`].
+#[derive(Default)]
+pub(crate) struct MovedLines {
+    ignore_whitespace: bool,
+    /// Runs of consecutive removed lines and of consecutive added lines.
+    removed: Vec<Run>,
+    added: Vec<Run>,
+}
+
+/// Consecutive lines of a hunk that are all removed or all added.
+struct Run {
+    /// Index of the file the lines are in, as given to [`MovedLines::add`].
+    file: usize,
+    lines: Vec<String>,
+}
+
+impl MovedLines {
+    /// Adds the removed and added lines of `patch`, a diff of the file `file`.
+    pub(crate) fn add(&mut self, file: usize, patch: &Patch) -> Result<(), git2::Error> {
+        for hunk in 0..patch.num_hunks() {
+            let mut run: Option<(char, Vec<String>)> = None;
+            for line in 0..patch.num_lines_in_hunk(hunk)? {
+                let line = patch.line_in_hunk(hunk, line)?;
+                let origin = line.origin();
+                if run.as_ref().is_some_and(|(o, _)| *o != origin) {
+                    self.push(file, run.take());
+                }
+                if matches!(origin, '+' | '-') {
+                    let content = self.normalize(line.content());
+                    run.get_or_insert_with(|| (origin, Vec::new()))
+                        .1
+                        .push(content);
+                }
+            }
+            self.push(file, run);
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, file: usize, run: Option<(char, Vec<String>)>) {
+        match run {
+            Some(('-', lines)) => self.removed.push(Run { file, lines }),
+            Some(('+', lines)) => self.added.push(Run { file, lines }),
+            _ => (),
+        }
+    }
+
+    fn normalize(&self, content: &[u8]) -> String {
+        let line = String::from_utf8_lossy(content);
+        if self.ignore_whitespace {
+            line.split_whitespace().collect()
+        } else {
+            line.trim_end_matches(['\n', '\r']).to_string()
+        }
+    }
+
+    /// Lines added and removed by a move in each file, by index. Each added
+    /// block is matched with the longest identical removed block not matched
+    /// yet.
+    pub(crate) fn count(&self) -> HashMap<usize, (usize, usize)> {
+        let mut positions: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+        for (run, removed) in self.removed.iter().enumerate() {
+            for (position, line) in removed.lines.iter().enumerate() {
+                positions.entry(line).or_default().push((run, position));
+            }
+        }
+        let mut matched: Vec<Vec<bool>> = self
+            .removed
+            .iter()
+            .map(|run| vec![false; run.lines.len()])
+            .collect();
+
+        let mut moved: HashMap<usize, (usize, usize)> = HashMap::new();
+        for added in &self.added {
+            let mut start = 0;
+            while start < added.lines.len() {
+                let candidates = positions.get(added.lines[start].as_str());
+                let longest = candidates
+                    .into_iter()
+                    .flatten()
+                    .map(|&(run, position)| {
+                        let removed = &self.removed[run].lines[position..];
+                        let len = added.lines[start..]
+                            .iter()
+                            .zip(removed)
+                            .zip(&matched[run][position..])
+                            .take_while(|((a, r), matched)| a == r && !**matched)
+                            .count();
+                        (run, position, len)
+                    })
+                    .max_by_key(|&(_, _, len)| len);
+                let block = longest.filter(|&(_, _, len)| {
+                    let alnum: usize = added.lines[start..start + len]
+                        .iter()
+                        .map(|line| line.chars().filter(|c| c.is_alphanumeric()).count())
+                        .sum();
+                    alnum >= MIN_MOVED_ALNUM
+                });
+                let Some((run, position, len)) = block else {
+                    start += 1;
+                    continue;
+                };
+                matched[run][position..position + len].fill(true);
+                moved.entry(added.file).or_default().0 += len;
+                moved.entry(self.removed[run].file).or_default().1 += len;
+                start += len;
+            }
+        }
+        moved
+    }
+}
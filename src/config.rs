@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::tui::keymap::Keymap;
+use crate::tui::theme::{Theme, ThemeConfig};
+
+/// Settings read from the config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Name of the TUI theme, `dark` if not set.
+    pub theme: Option<String>,
+    /// Custom themes, by name.
+    pub themes: HashMap<String, ThemeConfig>,
+    /// Keys of the TUI actions that differ from the defaults, by action name.
+    pub keys: HashMap<String, Vec<String>>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/git-stats/config.toml`, falling back to
+    /// `~/.config/git-stats/config.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_dir.join("git-stats").join("config.toml"))
+    }
+
+    /// Reads the config at `path`, or at the default path if `None`. A missing
+    /// file at the default path gives the default config.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Config::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+
+    /// Theme to draw the TUI with. `name` overrides the one of the config, and
+    /// colors are disabled whenever `NO_COLOR` is set.
+    pub fn theme(&self, name: Option<&str>) -> Result<Theme> {
+        if no_color() {
+            return Ok(Theme::no_color());
+        }
+        let name = name.or(self.theme.as_deref()).unwrap_or("dark");
+        Theme::resolve(name, &self.themes)
+    }
+
+    /// Keys of the TUI, the defaults with the ones of the config applied.
+    pub fn keymap(&self) -> Result<Keymap> {
+        Keymap::with_overrides(&self.keys).context("Invalid [keys] in the config file")
+    }
+}
+
+/// Whether `NO_COLOR` is set to a non-empty value, see https://no-color.org.
+fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use git2::Oid;
+
+/// Ways computing the stats of a repository can fail.
+#[derive(Debug, thiserror::Error)]
+pub enum StatsError {
+    #[error("No git repository found at {}", path.display())]
+    RepoNotFound {
+        path: PathBuf,
+        #[source]
+        source: git2::Error,
+    },
+    #[error("Unknown revision '{revspec}'")]
+    BadRevspec {
+        revspec: String,
+        #[source]
+        source: git2::Error,
+    },
+    #[error("The repository has no commits yet")]
+    EmptyRepository,
+    #[error("HEAD points to '{branch}', which has no commits yet")]
+    UnbornHead { branch: String },
+    #[error("Commit {oid} has an invalid timestamp")]
+    InvalidTimestamp { oid: Oid },
+    #[error("Failed to diff commit {oid}")]
+    DiffFailed {
+        oid: Oid,
+        #[source]
+        source: git2::Error,
+    },
+    #[error("Failed to read the commits to ignore from {}: {source}", path.display())]
+    IgnoreRevsFile {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Failed to watch {} for changes", path.display())]
+    Watch {
+        path: PathBuf,
+        #[source]
+        source: notify::Error,
+    },
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+}
+
+pub type Result<T, E = StatsError> = std::result::Result<T, E>;
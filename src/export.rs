@@ -0,0 +1,345 @@
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::languages;
+use crate::users::UserInfo;
+
+/// File format of an export, picked from the extension of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("md" | "markdown") => Ok(ExportFormat::Markdown),
+            Some("json") => Ok(ExportFormat::Json),
+            Some("csv") => Ok(ExportFormat::Csv),
+            Some(other) => bail!("Unknown export format '.{}', use .md, .json or .csv", other),
+            None => bail!("Missing file extension, use .md, .json or .csv"),
+        }
+    }
+}
+
+/// The stats of a user, as they are exported.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserSummary {
+    pub name: String,
+    pub email: String,
+    pub commits: u32,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub net_lines: i64,
+    pub avg_commits_per_day: f32,
+    pub avg_commits_per_month: f32,
+    pub avg_lines_added_per_day: f32,
+    pub avg_lines_removed_per_day: f32,
+    /// Lines changed in generated, vendored and binary files, or by ignored
+    /// commits, left out of the counts above.
+    pub excluded_lines_added: usize,
+    pub excluded_lines_removed: usize,
+    /// Files renamed or copied by the user, whose unchanged lines aren't
+    /// counted.
+    pub renames: usize,
+    pub copies: usize,
+    pub first_commit: Option<NaiveDate>,
+    pub last_commit: Option<NaiveDate>,
+    /// Percentage of the commits of the repository made by the user.
+    pub share_of_commits: f64,
+    /// Lines changed by the user in each language, biggest share first. Left
+    /// out of CSV exports, which have a single row per user.
+    #[serde(skip)]
+    pub languages: Vec<LanguageSummary>,
+}
+
+/// The lines changed by a user in a language, as they are exported.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LanguageSummary {
+    pub language: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Percentage of the line changes of the user made in this language.
+    pub share: f64,
+}
+
+/// A [`UserSummary`] with its languages, for JSON exports.
+#[derive(Serialize)]
+struct JsonUserSummary<'a> {
+    #[serde(flatten)]
+    user: &'a UserSummary,
+    languages: &'a [LanguageSummary],
+}
+
+impl<'a> From<&'a UserSummary> for JsonUserSummary<'a> {
+    fn from(user: &'a UserSummary) -> Self {
+        JsonUserSummary {
+            user,
+            languages: &user.languages,
+        }
+    }
+}
+
+impl UserSummary {
+    /// Summary of `user`, out of the `total_commits` of the repository.
+    pub fn new(user: &UserInfo, total_commits: usize) -> Self {
+        let stats = &user.stats;
+        let share_of_commits = if total_commits > 0 {
+            stats.total_commits as f64 * 100.0 / total_commits as f64
+        } else {
+            0.0
+        };
+        UserSummary {
+            name: user.name.clone(),
+            email: user.email.clone(),
+            commits: stats.total_commits,
+            lines_added: stats.total_lines_added,
+            lines_removed: stats.total_lines_removed,
+            net_lines: stats.net_lines(),
+            avg_commits_per_day: stats.average_commits_per_day,
+            avg_commits_per_month: stats.average_commits_per_month,
+            avg_lines_added_per_day: stats.average_lines_added_per_day,
+            avg_lines_removed_per_day: stats.average_lines_removed_per_day,
+            excluded_lines_added: stats.excluded_lines_added,
+            excluded_lines_removed: stats.excluded_lines_removed,
+            renames: stats.renames,
+            copies: stats.copies,
+            first_commit: stats.first_commit,
+            last_commit: stats.last_commit,
+            share_of_commits,
+            languages: languages::distribution(&stats.languages)
+                .into_iter()
+                .map(|(language, share)| LanguageSummary {
+                    language: language.to_string(),
+                    lines_added: stats.languages[language].lines_added,
+                    lines_removed: stats.languages[language].lines_removed,
+                    share,
+                })
+                .collect(),
+        }
+    }
+
+    /// Formatted value of each field, in the order of [`METRIC_LABELS`].
+    fn values(&self) -> [String; 17] {
+        let date = |d: Option<NaiveDate>| d.map(|d| d.to_string()).unwrap_or_default();
+        [
+            self.name.clone(),
+            self.email.clone(),
+            self.commits.to_string(),
+            self.lines_added.to_string(),
+            self.lines_removed.to_string(),
+            self.net_lines.to_string(),
+            format!("{:.2}", self.avg_commits_per_day),
+            format!("{:.2}", self.avg_commits_per_month),
+            format!("{:.2}", self.avg_lines_added_per_day),
+            format!("{:.2}", self.avg_lines_removed_per_day),
+            self.excluded_lines_added.to_string(),
+            self.excluded_lines_removed.to_string(),
+            self.renames.to_string(),
+            self.copies.to_string(),
+            date(self.first_commit),
+            date(self.last_commit),
+            format!("{:.2}%", self.share_of_commits),
+        ]
+    }
+}
+
+/// Labels of the fields of a [`UserSummary`] in Markdown exports.
+const METRIC_LABELS: [&str; 17] = [
+    "Name",
+    "Email",
+    "Commits",
+    "Lines added",
+    "Lines removed",
+    "Net lines",
+    "Avg commits per day",
+    "Avg commits per month",
+    "Avg lines added per day",
+    "Avg lines removed per day",
+    "Excluded lines added",
+    "Excluded lines removed",
+    "Files renamed",
+    "Files copied",
+    "First commit",
+    "Last commit",
+    "Share of commits",
+];
+
+impl LanguageSummary {
+    /// Formatted value of each field, in the order of [`LANGUAGE_LABELS`].
+    fn values(&self) -> [String; 4] {
+        [
+            self.language.clone(),
+            self.lines_added.to_string(),
+            self.lines_removed.to_string(),
+            format!("{:.2}%", self.share),
+        ]
+    }
+}
+
+/// Labels of the fields of a [`LanguageSummary`] in Markdown exports.
+const LANGUAGE_LABELS: [&str; 4] = ["Language", "Lines added", "Lines removed", "Share"];
+
+/// Every language of `users`, the ones with the most line changes first.
+fn languages_by_share(users: &[UserSummary]) -> Vec<&str> {
+    let mut changes: Vec<(&str, usize)> = Vec::new();
+    for language in users.iter().flat_map(|u| &u.languages) {
+        let lines = language.lines_added + language.lines_removed;
+        match changes.iter_mut().find(|(l, _)| *l == language.language) {
+            Some((_, total)) => *total += lines,
+            None => changes.push((&language.language, lines)),
+        }
+    }
+    changes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    changes.into_iter().map(|(language, _)| language).collect()
+}
+
+/// Something that can be exported from the TUI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Export {
+    /// The stats of a single user.
+    User(UserSummary),
+    /// Users side by side, one column per user in Markdown.
+    Comparison(Vec<UserSummary>),
+    /// A list of users, one row per user.
+    Users(Vec<UserSummary>),
+}
+
+impl Export {
+    /// Writes the export to `path`, in the format matching its extension.
+    /// Fails if `path` already exists rather than overwriting it.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = ExportFormat::from_path(path)?;
+        let mut file = std::fs::File::create_new(path).map_err(|err| match err.kind() {
+            std::io::ErrorKind::AlreadyExists => anyhow!("{} already exists", path.display()),
+            _ => anyhow::Error::new(err).context(format!("Failed to create {}", path.display())),
+        })?;
+        self.write(format, &mut file)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn write(&self, format: ExportFormat, out: &mut impl Write) -> Result<()> {
+        let users = match self {
+            Export::User(user) => std::slice::from_ref(user),
+            Export::Comparison(users) | Export::Users(users) => users.as_slice(),
+        };
+        match format {
+            ExportFormat::Json => {
+                match self {
+                    Export::User(user) => {
+                        serde_json::to_writer_pretty(&mut *out, &JsonUserSummary::from(user))?
+                    }
+                    _ => {
+                        let users: Vec<_> = users.iter().map(JsonUserSummary::from).collect();
+                        serde_json::to_writer_pretty(&mut *out, &users)?
+                    }
+                }
+                writeln!(out)?;
+            }
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(out);
+                for user in users {
+                    writer.serialize(user)?;
+                }
+                writer.flush()?;
+            }
+            ExportFormat::Markdown => match self {
+                Export::User(user) => {
+                    writeln!(out, "# {} <{}>", user.name, user.email)?;
+                    writeln!(out)?;
+                    write_markdown_row(out, ["Metric", "Value"])?;
+                    write_markdown_row(out, ["---", "---"])?;
+                    for (label, value) in METRIC_LABELS.iter().zip(user.values()).skip(2) {
+                        write_markdown_row(out, [label.to_string(), value])?;
+                    }
+                    if !user.languages.is_empty() {
+                        writeln!(out)?;
+                        writeln!(out, "## Languages")?;
+                        writeln!(out)?;
+                        write_markdown_row(out, LANGUAGE_LABELS)?;
+                        write_markdown_row(out, LANGUAGE_LABELS.map(|_| "---"))?;
+                        for language in &user.languages {
+                            write_markdown_row(out, language.values())?;
+                        }
+                    }
+                }
+                Export::Comparison(users) => {
+                    let header =
+                        std::iter::once(String::new()).chain(users.iter().map(|u| u.name.clone()));
+                    write_markdown_row(out, header)?;
+                    write_markdown_row(out, std::iter::repeat_n("---", users.len() + 1))?;
+                    let values: Vec<_> = users.iter().map(|u| u.values()).collect();
+                    for (row, label) in METRIC_LABELS.iter().enumerate().skip(1) {
+                        let cells = std::iter::once(label.to_string())
+                            .chain(values.iter().map(|v| v[row].clone()));
+                        write_markdown_row(out, cells)?;
+                    }
+                    let languages = languages_by_share(users);
+                    if !languages.is_empty() {
+                        writeln!(out)?;
+                        let header = std::iter::once("Language".to_string())
+                            .chain(users.iter().map(|u| u.name.clone()));
+                        write_markdown_row(out, header)?;
+                        write_markdown_row(out, std::iter::repeat_n("---", users.len() + 1))?;
+                        for language in languages {
+                            let cells = std::iter::once(language.to_string()).chain(
+                                users.iter().map(|u| {
+                                    let share = u
+                                        .languages
+                                        .iter()
+                                        .find(|l| l.language == language)
+                                        .map_or(0.0, |l| l.share);
+                                    format!("{:.2}%", share)
+                                }),
+                            );
+                            write_markdown_row(out, cells)?;
+                        }
+                    }
+                }
+                Export::Users(users) => {
+                    write_markdown_row(out, METRIC_LABELS)?;
+                    write_markdown_row(out, METRIC_LABELS.map(|_| "---"))?;
+                    for user in users {
+                        write_markdown_row(out, user.values())?;
+                    }
+                    if users.iter().any(|u| !u.languages.is_empty()) {
+                        writeln!(out)?;
+                        let header = std::iter::once("Name").chain(LANGUAGE_LABELS);
+                        write_markdown_row(out, header)?;
+                        write_markdown_row(out, std::iter::repeat_n("---", 6))?;
+                        for user in users {
+                            for language in &user.languages {
+                                let cells =
+                                    std::iter::once(user.name.clone()).chain(language.values());
+                                write_markdown_row(out, cells)?;
+                            }
+                        }
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+fn write_markdown_row<T: AsRef<str>>(
+    out: &mut impl Write,
+    cells: impl IntoIterator<Item = T>,
+) -> Result<()> {
+    write!(out, "|")?;
+    for cell in cells {
+        write!(out, " {} |", cell.as_ref().replace('|', "\\|"))?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
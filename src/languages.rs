@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Name of the language of files that match none of the known ones.
+pub const OTHER: &str = "Other";
+
+/// Files recognized by their whole name, before looking at the extension.
+const FILENAMES: [(&str, &str); 16] = [
+    ("Makefile", "Makefile"),
+    ("GNUmakefile", "Makefile"),
+    ("makefile", "Makefile"),
+    ("CMakeLists.txt", "CMake"),
+    ("Dockerfile", "Dockerfile"),
+    ("Containerfile", "Dockerfile"),
+    ("Jenkinsfile", "Groovy"),
+    ("Rakefile", "Ruby"),
+    ("Gemfile", "Ruby"),
+    ("Vagrantfile", "Ruby"),
+    ("BUILD", "Starlark"),
+    ("BUILD.bazel", "Starlark"),
+    ("WORKSPACE", "Starlark"),
+    ("Justfile", "Just"),
+    ("justfile", "Just"),
+    ("meson.build", "Meson"),
+];
+
+/// Languages by file extension, lowercase.
+const EXTENSIONS: [(&str, &str); 74] = [
+    ("rs", "Rust"),
+    ("c", "C"),
+    ("h", "C"),
+    ("cc", "C++"),
+    ("cpp", "C++"),
+    ("cxx", "C++"),
+    ("hh", "C++"),
+    ("hpp", "C++"),
+    ("hxx", "C++"),
+    ("cs", "C#"),
+    ("go", "Go"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("kts", "Kotlin"),
+    ("scala", "Scala"),
+    ("groovy", "Groovy"),
+    ("gradle", "Groovy"),
+    ("swift", "Swift"),
+    ("m", "Objective-C"),
+    ("mm", "Objective-C++"),
+    ("py", "Python"),
+    ("pyi", "Python"),
+    ("rb", "Ruby"),
+    ("php", "PHP"),
+    ("pl", "Perl"),
+    ("pm", "Perl"),
+    ("lua", "Lua"),
+    ("r", "R"),
+    ("jl", "Julia"),
+    ("dart", "Dart"),
+    ("ex", "Elixir"),
+    ("exs", "Elixir"),
+    ("erl", "Erlang"),
+    ("hs", "Haskell"),
+    ("ml", "OCaml"),
+    ("mli", "OCaml"),
+    ("fs", "F#"),
+    ("clj", "Clojure"),
+    ("zig", "Zig"),
+    ("nim", "Nim"),
+    ("js", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("cjs", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("mts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("vue", "Vue"),
+    ("svelte", "Svelte"),
+    ("html", "HTML"),
+    ("htm", "HTML"),
+    ("css", "CSS"),
+    ("scss", "SCSS"),
+    ("sass", "SCSS"),
+    ("less", "Less"),
+    ("sh", "Shell"),
+    ("bash", "Shell"),
+    ("zsh", "Shell"),
+    ("fish", "Shell"),
+    ("ps1", "PowerShell"),
+    ("sql", "SQL"),
+    ("proto", "Protocol Buffers"),
+    ("nix", "Nix"),
+    ("tf", "HCL"),
+    ("hcl", "HCL"),
+    ("md", "Markdown"),
+    ("markdown", "Markdown"),
+    ("rst", "reStructuredText"),
+    ("tex", "TeX"),
+    ("json", "JSON"),
+    ("toml", "TOML"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("xml", "XML"),
+];
+
+/// Language of the file at `path`, from its name or else its extension.
+/// [`OTHER`] if it's neither.
+pub fn language_of(path: &str) -> &'static str {
+    let path = Path::new(path);
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return OTHER;
+    };
+    if let Some((_, language)) = FILENAMES.iter().find(|(filename, _)| *filename == name) {
+        return language;
+    }
+    let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+        return OTHER;
+    };
+    let extension = extension.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(e, _)| *e == extension)
+        .map_or(OTHER, |(_, language)| language)
+}
+
+/// Lines changed in the files of a language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LanguageStats {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl LanguageStats {
+    /// Lines added plus removed.
+    pub fn line_changes(&self) -> usize {
+        self.lines_added + self.lines_removed
+    }
+}
+
+/// Share of the line changes of each language, as a percentage, biggest
+/// first. Languages with the same share are sorted by name.
+pub fn distribution(languages: &BTreeMap<&'static str, LanguageStats>) -> Vec<(&'static str, f64)> {
+    let total: usize = languages.values().map(LanguageStats::line_changes).sum();
+    let mut shares: Vec<_> = languages
+        .iter()
+        .map(|(language, stats)| {
+            let share = if total > 0 {
+                stats.line_changes() as f64 * 100.0 / total as f64
+            } else {
+                0.0
+            };
+            (*language, share)
+        })
+        .collect();
+    shares.sort_by(|a, b| b.1.total_cmp(&a.1));
+    shares
+}
@@ -1,2 +1,17 @@
+pub mod analysis;
+pub mod churn;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod languages;
+pub mod query;
+pub mod repo_stats;
+pub mod repository;
+pub mod submodules;
+pub mod timeseries;
 pub mod tui;
 pub mod users;
+pub mod watch;
+
+pub use error::StatsError;
+pub use query::{Report, StatsQuery};
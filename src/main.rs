@@ -1,19 +1,33 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
 use anyhow::Result;
 use clap::Parser;
 use git2::Repository;
 
 use git_stats::{
-    tui,
-    users::{self, update_user_stats},
+    StatsError, StatsQuery,
+    analysis::Analysis,
+    churn::{self, ChurnOptions, DEFAULT_SIMILARITY_THRESHOLD},
+    config::Config,
+    languages, repository, submodules,
+    tui::{self, app::App},
+    users::UserInfo,
+    watch::RefWatcher,
 };
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Path to the git repository
+    /// Path to the git repository, or to any directory inside it
     /// If not specified, the current directory will be used
     #[arg(short, long, default_value = ".")]
     pub repo: String,
+    /// Path to the git directory, e.g. a bare repository, like `git --git-dir`
+    /// The repository is not looked for in parent directories, and --repo is
+    /// not used. A relative path is relative to the current directory
+    #[arg(long, env = "GIT_DIR")]
+    pub git_dir: Option<PathBuf>,
     /// Branch to analyze
     /// If not specified, the current branch will be used
     #[arg(short, long, default_value = "HEAD")]
@@ -21,76 +35,318 @@ pub struct Args {
     /// Use TUI interface
     #[arg(short, long)]
     pub tui: bool,
+    /// Include the history of submodules, pinned at the commit recorded in the
+    /// superproject
+    #[arg(long)]
+    pub recurse_submodules: bool,
+    /// Leave the changes to the files matching this git pathspec out of the
+    /// line counts, e.g. `docs/*.svg`. Can be given several times
+    #[arg(long, value_name = "PATHSPEC")]
+    pub exclude: Vec<String>,
+    /// Count the lines of lock files, minified code and vendored directories,
+    /// which are left out by default
+    #[arg(long)]
+    pub no_default_excludes: bool,
+    /// How similar, in percent, a file must be to a deleted or changed one to
+    /// count as renamed or copied from it. Only its changed lines are counted
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = DEFAULT_SIMILARITY_THRESHOLD,
+        value_parser = clap::value_parser!(u16).range(0..=100)
+    )]
+    pub find_renames: u16,
+    /// Count renamed and copied files as deleted and added again
+    #[arg(long, conflicts_with = "find_renames")]
+    pub no_renames: bool,
+    /// Leave out the lines that only change whitespace, like `git diff -w`
+    #[arg(short = 'w', long)]
+    pub ignore_whitespace: bool,
+    /// Leave out the blocks of lines moved within or between files, like
+    /// `git diff --color-moved` shows them. Reindented blocks count as moved
+    /// with --ignore-whitespace
+    #[arg(long)]
+    pub ignore_moves: bool,
+    /// Leave out the changes of the commits listed in this file, in the format
+    /// of `.git-blame-ignore-revs`. Can be given several times. The file set
+    /// by the `blame.ignoreRevsFile` git config is read too
+    #[arg(long, value_name = "PATH")]
+    pub ignore_revs_file: Vec<PathBuf>,
+    /// Theme of the TUI: dark, light, high-contrast, none or a custom theme
+    /// from the config file
+    #[arg(long)]
+    pub theme: Option<String>,
+    /// Path to the config file
+    /// If not specified, $XDG_CONFIG_HOME/git-stats/config.toml will be used
+    #[arg(long)]
+    pub config: Option<PathBuf>,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     if std::env::var("RUST_BACKTRACE").is_err() {
         unsafe {
             std::env::set_var("RUST_BACKTRACE", "1");
         }
     }
 
-    let options = Args::parse();
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => report_error(&err),
+    }
+}
+
+/// Prints `err` with a hint on how to fix it, and returns the exit code for
+/// it. Every [`StatsError`] that stops the analysis has its own code.
+fn report_error(err: &anyhow::Error) -> ExitCode {
+    let Some(stats_error) = err.downcast_ref::<StatsError>() else {
+        eprintln!("Error: {:#}", err);
+        return ExitCode::FAILURE;
+    };
+    let (code, hint) = match stats_error {
+        StatsError::RepoNotFound { .. } => (
+            3,
+            "Run git-stats inside a git repository, or pass its path with --repo or --git-dir.",
+        ),
+        StatsError::BadRevspec { .. } => {
+            (4, "Pass an existing branch, tag or commit with --branch.")
+        }
+        StatsError::EmptyRepository => (5, "There is nothing to analyze before the first commit."),
+        StatsError::InvalidTimestamp { .. } => (
+            6,
+            "The commit is malformed, `git fsck` can tell more about it.",
+        ),
+        StatsError::DiffFailed { .. } => (
+            7,
+            "The repository may be corrupt, `git fsck` can tell more about it.",
+        ),
+        StatsError::Git(_) => (8, "libgit2 failed to read the repository."),
+        StatsError::UnbornHead { .. } => (9, "Pass an existing branch with --branch."),
+        StatsError::IgnoreRevsFile { .. } => (
+            10,
+            "List the commits to ignore by their full hash, one per line.",
+        ),
+        // Failing to watch the repository only turns live reload off, see `run`
+        StatsError::Watch { .. } => {
+            eprintln!("Error: {:#}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("Error: {}\n{}", stats_error, hint);
+    ExitCode::from(code)
+}
+
+fn run(options: Args) -> Result<()> {
+    let config = Config::load(options.config.as_deref())?;
+    let theme = config.theme(options.theme.as_deref())?;
+    let keymap = config.keymap()?;
     // Open the git repository
-    let path = options.repo;
-    let branch = options.branch;
+    let path = PathBuf::from(options.repo);
+    let repo = match &options.git_dir {
+        // Like git, relative to the current directory rather than to --repo
+        Some(git_dir) => repository::open_git_dir(git_dir)?,
+        None => repository::discover(path)?,
+    };
+    let mut ignored_revs = Vec::new();
+    for file in ignore_revs_files(&repo, &options.ignore_revs_file) {
+        ignored_revs.extend(churn::read_ignore_revs(&file)?);
+    }
+    let churn = ChurnOptions {
+        default_excludes: !options.no_default_excludes,
+        excludes: options.exclude.clone(),
+        similarity_threshold: (!options.no_renames).then_some(options.find_renames),
+        ignore_whitespace: options.ignore_whitespace,
+        ignore_moves: options.ignore_moves,
+        ignored_revs,
+        ..ChurnOptions::default()
+    };
+    if options.tui {
+        // Fail before starting the TUI if there is nothing to analyze
+        StatsQuery::new(&repo).revspec(&options.branch).tip()?;
+        // Launch the TUI right away and load the stats in the background
+        let analysis = if options.recurse_submodules {
+            Analysis::spawn_recursive(repo.path(), options.branch, churn)
+        } else {
+            Analysis::spawn_with(repo.path(), options.branch, churn)
+        };
+        let mut app = App::with_analysis(analysis)
+            .with_theme(theme)
+            .with_keymap(keymap);
+        // Keep the stats up to date while the TUI is open
+        match RefWatcher::new(&repo) {
+            Ok(watcher) => app = app.with_ref_watcher(watcher),
+            Err(err) => eprintln!(
+                "Warning: {:#}, the stats won't be reloaded when commits are added.",
+                anyhow::Error::new(err)
+            ),
+        }
+        tui::run_tui(app)?;
+        return Ok(());
+    }
+
+    let mut query = StatsQuery::new(&repo).revspec(&options.branch).churn(churn);
+    let submodules = if options.recurse_submodules {
+        submodules::open_submodules(&repo, query.tip()?)?
+    } else {
+        Default::default()
+    };
+    for submodule in &submodules.repos {
+        query = query.include(submodule.history());
+    }
+
+    let progress = indicatif::ProgressBar::new_spinner();
+    progress.set_message("Processing commits...");
+    progress.set_style(
+        indicatif::ProgressStyle::with_template(
+            "{spinner} {msg} commits {per_sec} [{elapsed_precise}]",
+        )?
+        .tick_chars("⠋⠙⠸⠼⠧⠇⠏"),
+    );
+    let report = query.run_with_progress(|_| progress.tick())?;
+    progress.finish_and_clear();
+    if let Some(boundary) = &report.partial {
+        eprintln!(
+            "Warning: the repository is a shallow clone, commits before {} are missing \
+             from the stats. Run `git fetch --unshallow` to get the whole history.",
+            boundary.date.format("%Y-%m-%d")
+        );
+    }
+
+    let mut reports = submodules::submodule_reports(&submodules, &report);
+    let mut users = report.users;
+    sort_and_filter(&mut users);
+
+    // Print the users in CLI mode
+    for user in users.iter() {
+        print_user(user);
+    }
+
+    for report in reports.iter_mut() {
+        sort_and_filter(&mut report.users);
+        let pinned = report
+            .pinned
+            .map(|oid| oid.to_string())
+            .unwrap_or_else(|| "HEAD".to_string());
+        println!(
+            "Submodule: {} ({}) @ {}",
+            report.name,
+            report.path.display(),
+            pinned
+        );
+        if let Some(warning) = &report.warning {
+            println!("  Warning: {warning}");
+        }
+        for user in report.users.iter() {
+            println!(
+                "  {} <{}>: {} commits, +{} -{}",
+                user.name,
+                user.email,
+                user.stats.total_commits,
+                user.stats.total_lines_added,
+                user.stats.total_lines_removed
+            );
+        }
+        println!("-----------------------------------");
+    }
 
-    let repo = Repository::open(path)?;
-    let mut users = users::get_users(&repo)?;
-    update_user_stats(&mut users, &repo, &branch)?;
+    Ok(())
+}
 
+fn sort_and_filter(users: &mut Vec<UserInfo>) {
     // Sort the users by total commits
-    users.sort_by(|a, b| b.stats.total_commits.cmp(&a.stats.total_commits));
+    users.sort_by_key(|u| std::cmp::Reverse(u.stats.total_commits));
 
     // Filter out user with no stats or less then 10 commits
     users.retain(|u| !u.has_not_stats() && u.stats.total_commits > 10);
+}
 
-    if options.tui {
-        // Launch TUI
-        tui::run_tui(users)?;
-    } else {
-        // Print the users in CLI mode
-        for user in users.iter() {
-            println!("User: {} <{}>", user.name, user.email);
-            println!("Total commits: {}", user.stats.total_commits);
-            println!(
-                "Average commits per day: {}",
-                user.stats.average_commits_per_day
-            );
-            println!(
-                "Average commits per month: {}",
-                user.stats.average_commits_per_month
-            );
-            println!(
-                "Average commits per year: {}",
-                user.stats.average_commits_per_year
-            );
-            println!("Total lines added: {}", user.stats.total_lines_added);
-            println!("Total lines removed: {}", user.stats.total_lines_removed);
-            println!(
-                "Average lines added per day: {}",
-                user.stats.average_lines_added_per_day
-            );
-            println!(
-                "Average lines removed per day: {}",
-                user.stats.average_lines_removed_per_day
-            );
-            println!(
-                "Average lines added per month: {}",
-                user.stats.average_lines_added_per_month
-            );
-            println!(
-                "Average lines removed per month: {}",
-                user.stats.average_lines_removed_per_month
-            );
+/// Files listing the commits to ignore: the `blame.ignoreRevsFile` of the git
+/// config when it exists, relative to the working tree like git reads it,
+/// then the `files` given on the command line.
+fn ignore_revs_files(repo: &Repository, files: &[PathBuf]) -> Vec<PathBuf> {
+    let configured = repo
+        .config()
+        .and_then(|config| config.get_path("blame.ignoreRevsFile"))
+        .ok()
+        .map(|file| match repo.workdir() {
+            Some(workdir) => workdir.join(file),
+            None => file,
+        })
+        .filter(|file| {
+            // `git blame` fails when the file is missing, e.g. on a branch
+            // older than it, but the stats are still worth computing
+            let exists = file.exists();
+            if !exists {
+                eprintln!(
+                    "Warning: {} set by blame.ignoreRevsFile does not exist, no commit is ignored \
+                     from it.",
+                    file.display()
+                );
+            }
+            exists
+        });
+    configured
+        .into_iter()
+        .chain(files.iter().cloned())
+        .collect()
+}
+
+fn print_user(user: &UserInfo) {
+    println!("User: {} <{}>", user.name, user.email);
+    println!("Total commits: {}", user.stats.total_commits);
+    println!(
+        "Average commits per day: {}",
+        user.stats.average_commits_per_day
+    );
+    println!(
+        "Average commits per month: {}",
+        user.stats.average_commits_per_month
+    );
+    println!(
+        "Average commits per year: {}",
+        user.stats.average_commits_per_year
+    );
+    println!("Total lines added: {}", user.stats.total_lines_added);
+    println!("Total lines removed: {}", user.stats.total_lines_removed);
+    if user.stats.excluded_lines_added + user.stats.excluded_lines_removed > 0 {
+        println!(
+            "Excluded lines (generated, vendored and binary files, ignored commits): +{} -{}",
+            user.stats.excluded_lines_added, user.stats.excluded_lines_removed
+        );
+    }
+    println!(
+        "Files renamed: {}, copied: {}",
+        user.stats.renames, user.stats.copies
+    );
+    println!(
+        "Average lines added per day: {}",
+        user.stats.average_lines_added_per_day
+    );
+    println!(
+        "Average lines removed per day: {}",
+        user.stats.average_lines_removed_per_day
+    );
+    println!(
+        "Average lines added per month: {}",
+        user.stats.average_lines_added_per_month
+    );
+    println!(
+        "Average lines removed per month: {}",
+        user.stats.average_lines_removed_per_month
+    );
+    println!(
+        "Average lines added per year: {}",
+        user.stats.average_lines_added_per_year
+    );
+    if !user.stats.languages.is_empty() {
+        println!("Languages:");
+        for (language, share) in languages::distribution(&user.stats.languages) {
+            let stats = user.stats.languages[language];
             println!(
-                "Average lines added per year: {}",
-                user.stats.average_lines_added_per_year
+                "  {:<16} +{:<8} -{:<8} {:.2}%",
+                language, stats.lines_added, stats.lines_removed, share
             );
-            println!("-----------------------------------");
         }
     }
-
-    Ok(())
+    println!("-----------------------------------");
 }
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+use git2::{Mailmap, Oid, Repository, RevparseMode, Revwalk};
+
+use crate::error::{Result, StatsError};
+use crate::repository::{self, ShallowBoundary};
+
+use crate::analysis::CommitRecord;
+use crate::churn::{Churn, ChurnOptions};
+use crate::repo_stats::RepoStats;
+use crate::timeseries::{Granularity, TimeSeries};
+use crate::users::{HistorySource, StatsAccumulator, UserInfo, get_file_changes, utc_from_commit};
+
+/// Everything computed by a [`StatsQuery`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    /// Every author of the commits, with their stats.
+    pub users: Vec<UserInfo>,
+    pub repo_stats: RepoStats,
+    /// Activity of the whole repository, see [`StatsQuery::granularity`].
+    pub activity: TimeSeries,
+    /// Set when the repository is a shallow clone, so the stats don't cover
+    /// the whole history.
+    pub partial: Option<ShallowBoundary>,
+    /// Authors of the commits of each named source, e.g. each submodule, with
+    /// their stats in that source only. See [`StatsQuery::include`].
+    pub sources: BTreeMap<String, Vec<UserInfo>>,
+}
+
+/// Which commits of a repository to compute stats for, and how. The history
+/// is walked once by [`StatsQuery::run`].
+pub struct StatsQuery<'r> {
+    repo: &'r Repository,
+    revspec: String,
+    hide: Option<Oid>,
+    since: Option<DateTime<Utc>>,
+    paths: Vec<String>,
+    mailmap: Option<Mailmap>,
+    includes: Vec<HistorySource<'r>>,
+    granularity: Granularity,
+    churn: ChurnOptions,
+}
+
+impl<'r> StatsQuery<'r> {
+    /// A query over the history of `HEAD`.
+    pub fn new(repo: &'r Repository) -> Self {
+        StatsQuery {
+            repo,
+            revspec: "HEAD".to_string(),
+            hide: None,
+            since: None,
+            paths: Vec::new(),
+            mailmap: None,
+            includes: Vec::new(),
+            granularity: Granularity::default(),
+            churn: ChurnOptions::default(),
+        }
+    }
+
+    /// Walks the history from `revspec`, a commit such as `main` or a range
+    /// such as `v1.0..main`.
+    pub fn revspec(mut self, revspec: impl Into<String>) -> Self {
+        self.revspec = revspec.into();
+        self
+    }
+
+    /// Leaves out the commits reachable from `oid`, e.g. the ones a previous
+    /// query already went through.
+    pub fn hide(mut self, oid: Oid) -> Self {
+        self.hide = Some(oid);
+        self
+    }
+
+    /// Only counts the commits made at or after `time`.
+    pub fn since(mut self, time: DateTime<Utc>) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Only counts the changes to files matching one of `paths`, which are
+    /// git pathspecs such as `src` or `*.rs`. Commits that change none of them
+    /// are left out.
+    pub fn paths(mut self, paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Resolves the authors with `mailmap`, so the commits of someone who used
+    /// several names or emails are counted together.
+    pub fn identity(mut self, mailmap: Mailmap) -> Self {
+        self.mailmap = Some(mailmap);
+        self
+    }
+
+    /// Also counts the history of `source`, e.g. the one of a submodule. When
+    /// the source is named, its commits are also counted on their own in
+    /// [`Report::sources`].
+    pub fn include(mut self, source: HistorySource<'r>) -> Self {
+        self.includes.push(source);
+        self
+    }
+
+    /// Size of the periods of [`Report::activity`], months by default.
+    pub fn granularity(mut self, granularity: Granularity) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// How the lines changed by each commit are counted, e.g. which files are
+    /// left out.
+    pub fn churn(mut self, options: ChurnOptions) -> Self {
+        self.churn = options;
+        self
+    }
+
+    /// Commit the revspec resolves to, the end of the range for a range.
+    pub fn tip(&self) -> Result<Oid> {
+        let spec = self.revparse()?;
+        let object = if spec.mode().contains(RevparseMode::SINGLE) {
+            spec.from()
+        } else {
+            spec.to()
+        };
+        object
+            .and_then(|object| object.peel_to_commit().ok())
+            .map(|commit| commit.id())
+            .ok_or_else(|| self.bad_revspec("the revision is not a commit"))
+    }
+
+    fn revparse(&self) -> Result<git2::Revspec<'r>> {
+        self.repo.revparse(&self.revspec).map_err(|source| {
+            if self.revspec == "HEAD" {
+                repository::head_error(self.repo, source)
+            } else if repository::has_no_commits(self.repo) {
+                StatsError::EmptyRepository
+            } else {
+                StatsError::BadRevspec {
+                    revspec: self.revspec.clone(),
+                    source,
+                }
+            }
+        })
+    }
+
+    fn bad_revspec(&self, reason: &str) -> StatsError {
+        StatsError::BadRevspec {
+            revspec: self.revspec.clone(),
+            source: git2::Error::from_str(reason),
+        }
+    }
+
+    /// Commits to walk, the ones of the repository then the ones of each
+    /// included source.
+    pub(crate) fn revwalks(&self) -> Result<Vec<Revwalk<'r>>> {
+        let mut revwalks = vec![self.revwalk()?];
+        for source in &self.includes {
+            revwalks.push(source.revwalk()?);
+        }
+        Ok(revwalks)
+    }
+
+    /// Commits of the repository to walk, without the included sources.
+    fn revwalk(&self) -> Result<Revwalk<'r>> {
+        let mut revwalk = self.repo.revwalk()?;
+        let spec = self.revparse()?;
+        if spec.mode().contains(RevparseMode::MERGE_BASE) {
+            return Err(self.bad_revspec("symmetric differences are not supported"));
+        }
+        if spec.mode().contains(RevparseMode::RANGE)
+            && let Some(from) = spec.from()
+        {
+            let from = from
+                .peel_to_commit()
+                .map_err(|_| self.bad_revspec("the start of the range is not a commit"))?;
+            revwalk.hide(from.id())?;
+        }
+        revwalk.push(self.tip()?)?;
+        if let Some(hide) = self.hide {
+            revwalk.hide(hide)?;
+        }
+        Ok(revwalk)
+    }
+
+    /// Walks the history once, with an item for every commit: its record, or
+    /// `None` if the query leaves it out.
+    pub fn walk(&self) -> Result<impl Iterator<Item = Result<Option<CommitRecord>>> + '_> {
+        let main = HistorySource::head(self.repo);
+        let mut walks = Vec::new();
+        for (source, revwalk) in std::iter::once(&main)
+            .chain(&self.includes)
+            .zip(self.revwalks()?)
+        {
+            walks.push((*source, revwalk, Churn::new(source.repo, &self.churn)?));
+        }
+        Ok(walks.into_iter().flat_map(move |(source, revwalk, churn)| {
+            revwalk.map(move |oid| self.record(source, oid?, &churn))
+        }))
+    }
+
+    fn record(
+        &self,
+        source: HistorySource,
+        oid: Oid,
+        churn: &Churn,
+    ) -> Result<Option<CommitRecord>> {
+        let repo = source.repo;
+        let commit = repo.find_commit(oid)?;
+        if let Some(since) = self.since
+            && utc_from_commit(&commit)? < since
+        {
+            return Ok(None);
+        }
+        let changes = get_file_changes(repo, &commit, &self.paths, churn)?;
+        if !self.paths.is_empty() && changes.counted.is_empty() && changes.excluded.is_empty() {
+            return Ok(None);
+        }
+        let author = match &self.mailmap {
+            Some(mailmap) => mailmap.resolve_signature(&commit.author())?,
+            None => commit.author(),
+        };
+        let mut record = CommitRecord::new(&commit, &author, changes)?;
+        record.source = source.name.map(str::to_string);
+        Ok(Some(record))
+    }
+
+    /// Where the history of the revspec was cut, if the repository is a
+    /// shallow clone.
+    pub fn shallow_boundary(&self) -> Result<Option<ShallowBoundary>> {
+        repository::shallow_boundary(self.repo, self.tip()?)
+    }
+
+    pub fn run(&self) -> Result<Report> {
+        self.run_with_progress(|_| ())
+    }
+
+    /// Like [`StatsQuery::run`], calling `progress` with the number of commits
+    /// walked so far after each one.
+    pub fn run_with_progress(&self, mut progress: impl FnMut(usize)) -> Result<Report> {
+        let mut accumulator = StatsAccumulator::new();
+        for (walked, record) in (1..).zip(self.walk()?) {
+            if let Some(record) = record? {
+                accumulator.add(record);
+            }
+            progress(walked);
+        }
+
+        let users = accumulator.users().to_vec();
+        let sources = accumulator
+            .sources()
+            .map(|(name, users)| (name.to_string(), users.to_vec()))
+            .collect();
+        let repo_stats = accumulator.repo_stats().clone();
+        Ok(Report {
+            users,
+            sources,
+            partial: self.shallow_boundary()?,
+            activity: TimeSeries::new(&repo_stats, self.granularity),
+            repo_stats,
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Months, prelude::*};
+
+use crate::analysis::{CommitRecord, FileChange};
+use crate::users::UserInfo;
+
+/// Stats of the whole repository, over every author.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepoStats {
+    pub total_commits: usize,
+    pub total_lines_added: usize,
+    pub total_lines_removed: usize,
+    pub first_commit: Option<DateTime<Utc>>,
+    pub last_commit: Option<DateTime<Utc>>,
+    /// Emails of every author.
+    pub authors: HashSet<String>,
+    /// Number of commits on each day with activity.
+    pub commits_per_day: BTreeMap<NaiveDate, usize>,
+    /// Lines added plus removed on each day with activity.
+    pub line_changes_per_day: BTreeMap<NaiveDate, usize>,
+    pub files: HashMap<String, FileStats>,
+    /// Lines changed in the files left out of the counts above, see
+    /// [`ChurnOptions`](crate::churn::ChurnOptions).
+    pub excluded_lines_added: usize,
+    pub excluded_lines_removed: usize,
+    /// Files left out of the counts, binary ones included.
+    pub excluded_files: HashMap<String, FileStats>,
+}
+
+/// How often a single file was changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileStats {
+    pub commits: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Emails of the authors that changed the file.
+    pub authors: HashSet<String>,
+}
+
+impl RepoStats {
+    /// Builds the stats from the commits of `users`.
+    pub fn from_users(users: &[UserInfo]) -> Self {
+        let mut stats = RepoStats::default();
+        for commit in users.iter().flat_map(|u| u.stats.commits.iter()) {
+            stats.add(commit);
+        }
+        stats
+    }
+
+    pub fn add(&mut self, commit: &CommitRecord) {
+        self.total_commits += 1;
+        self.total_lines_added += commit.lines_added;
+        self.total_lines_removed += commit.lines_removed;
+        self.first_commit = Some(
+            self.first_commit
+                .map_or(commit.time, |t| t.min(commit.time)),
+        );
+        self.last_commit = Some(self.last_commit.map_or(commit.time, |t| t.max(commit.time)));
+        if !self.authors.contains(&commit.author_email) {
+            self.authors.insert(commit.author_email.clone());
+        }
+
+        let date = commit.time.date_naive();
+        *self.commits_per_day.entry(date).or_default() += 1;
+        *self.line_changes_per_day.entry(date).or_default() +=
+            commit.lines_added + commit.lines_removed;
+
+        add_files(&mut self.files, commit, &commit.files);
+        add_files(&mut self.excluded_files, commit, &commit.excluded_files);
+        for change in &commit.excluded_files {
+            self.excluded_lines_added += change.lines_added;
+            self.excluded_lines_removed += change.lines_removed;
+        }
+    }
+
+    /// Files sorted by how often they were changed, most changed first.
+    pub fn hotspots(&self) -> Vec<(&str, &FileStats)> {
+        let mut files: Vec<_> = self
+            .files
+            .iter()
+            .map(|(path, stats)| (path.as_str(), stats))
+            .collect();
+        files.sort_by(|a, b| {
+            b.1.commits
+                .cmp(&a.1.commits)
+                .then_with(|| {
+                    (b.1.lines_added + b.1.lines_removed)
+                        .cmp(&(a.1.lines_added + a.1.lines_removed))
+                })
+                .then_with(|| a.0.cmp(b.0))
+        });
+        files
+    }
+
+    /// Number of commits per month, keyed by the first day of the month.
+    /// Months without commits between the first and last one are included.
+    pub fn commits_per_month(&self) -> BTreeMap<NaiveDate, usize> {
+        let mut months = BTreeMap::new();
+        let (Some(first), Some(last)) = (
+            self.commits_per_day.keys().next(),
+            self.commits_per_day.keys().next_back(),
+        ) else {
+            return months;
+        };
+
+        let mut month = first.with_day(1).unwrap_or(*first);
+        while month <= *last {
+            months.insert(month, 0);
+            month = month + Months::new(1);
+        }
+        for (date, commits) in &self.commits_per_day {
+            *months.entry(date.with_day(1).unwrap_or(*date)).or_default() += commits;
+        }
+        months
+    }
+
+    /// Lines added plus removed per year.
+    pub fn line_changes_per_year(&self) -> BTreeMap<i32, usize> {
+        let mut years = BTreeMap::new();
+        for (date, changes) in &self.line_changes_per_day {
+            *years.entry(date.year()).or_default() += changes;
+        }
+        years
+    }
+}
+
+fn add_files(
+    files: &mut HashMap<String, FileStats>,
+    commit: &CommitRecord,
+    changes: &[FileChange],
+) {
+    for change in changes {
+        let file = files.entry(change.path.clone()).or_default();
+        file.commits += 1;
+        file.lines_added += change.lines_added;
+        file.lines_removed += change.lines_removed;
+        if !file.authors.contains(&commit.author_email) {
+            file.authors.insert(commit.author_email.clone());
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use git2::{ErrorCode, Oid, Repository, RepositoryOpenFlags};
+
+use crate::error::{Result, StatsError};
+use crate::users::utc_from_commit;
+
+/// Where the history of a shallow clone was cut, see `git clone --depth`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShallowBoundary {
+    /// Commits whose parents weren't fetched.
+    pub commits: Vec<Oid>,
+    /// Date of the most recent of those commits. The history before it may be
+    /// missing.
+    pub date: DateTime<Utc>,
+}
+
+/// Opens the repository at `path`, which is either its working directory or,
+/// for a bare repository, its git directory.
+pub fn open(path: impl AsRef<Path>) -> Result<Repository> {
+    let path = path.as_ref();
+    Repository::open(path).map_err(|source| open_error(path, source))
+}
+
+/// Opens the repository containing `path`, looking in its parents the way git
+/// does. Works from a subdirectory, in a bare repository and in a linked
+/// worktree.
+pub fn discover(path: impl AsRef<Path>) -> Result<Repository> {
+    let path = path.as_ref();
+    Repository::open_ext(path, RepositoryOpenFlags::empty(), ceiling_dirs())
+        .map_err(|source| open_error(path, source))
+}
+
+/// Opens the git directory at `path` without looking anywhere else, like
+/// `git --git-dir`.
+pub fn open_git_dir(path: impl AsRef<Path>) -> Result<Repository> {
+    let path = path.as_ref();
+    Repository::open_ext(
+        path,
+        RepositoryOpenFlags::NO_SEARCH | RepositoryOpenFlags::NO_DOTGIT,
+        std::iter::empty::<&OsStr>(),
+    )
+    .map_err(|source| open_error(path, source))
+}
+
+/// Directories the discovery must not go up to, from
+/// `GIT_CEILING_DIRECTORIES`.
+fn ceiling_dirs() -> Vec<PathBuf> {
+    std::env::var_os("GIT_CEILING_DIRECTORIES")
+        .map(|dirs| std::env::split_paths(&dirs).collect())
+        .unwrap_or_default()
+}
+
+fn open_error(path: &Path, source: git2::Error) -> StatsError {
+    match source.code() {
+        ErrorCode::NotFound => StatsError::RepoNotFound {
+            path: path.to_path_buf(),
+            source,
+        },
+        _ => StatsError::Git(source),
+    }
+}
+
+/// Whether `repo` has no commits at all, e.g. right after `git init`.
+pub(crate) fn has_no_commits(repo: &Repository) -> bool {
+    repo.references()
+        .map(|mut references| references.all(|r| r.and_then(|r| r.peel_to_commit()).is_err()))
+        .unwrap_or(false)
+}
+
+/// Explains why `HEAD` of `repo` couldn't be resolved to a commit.
+pub(crate) fn head_error(repo: &Repository, source: git2::Error) -> StatsError {
+    if has_no_commits(repo) {
+        return StatsError::EmptyRepository;
+    }
+    let unborn_branch = repo
+        .find_reference("HEAD")
+        .ok()
+        .and_then(|head| head.symbolic_target().map(str::to_string))
+        .filter(|target| repo.find_reference(target).is_err());
+    match unborn_branch {
+        Some(target) => StatsError::UnbornHead {
+            branch: target
+                .strip_prefix("refs/heads/")
+                .unwrap_or(&target)
+                .to_string(),
+        },
+        None => StatsError::Git(source),
+    }
+}
+
+/// Commits of a shallow clone whose parents weren't fetched, as listed in its
+/// `shallow` file. Empty for a complete clone.
+pub fn shallow_commits(repo: &Repository) -> Vec<Oid> {
+    if !repo.is_shallow() {
+        return Vec::new();
+    }
+    std::fs::read_to_string(repo.commondir().join("shallow"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| Oid::from_str(line.trim()).ok())
+        .collect()
+}
+
+/// Where the history reachable from `tip` was cut, if `repo` is a shallow clone
+/// and the cut is in that history.
+pub fn shallow_boundary(repo: &Repository, tip: Oid) -> Result<Option<ShallowBoundary>> {
+    let mut commits = Vec::new();
+    let mut date = None;
+    for oid in shallow_commits(repo) {
+        if oid != tip && !repo.graph_descendant_of(tip, oid)? {
+            continue;
+        }
+        let time = utc_from_commit(&repo.find_commit(oid)?)?;
+        date = Some(date.map_or(time, |date: DateTime<Utc>| date.max(time)));
+        commits.push(oid);
+    }
+    Ok(date.map(|date| ShallowBoundary { commits, date }))
+}
@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+
+use git2::{Oid, Repository, Tree};
+
+use crate::error::Result;
+use crate::query::Report;
+use crate::users::{HistorySource, UserInfo};
+
+/// A submodule that could be opened from its superproject.
+pub struct SubmoduleRepo {
+    /// Name of the submodule, prefixed with its parents' names when nested.
+    pub name: String,
+    /// Path of the submodule relative to the top-level superproject.
+    pub path: PathBuf,
+    /// Commit recorded for the submodule in the analyzed commit of its
+    /// superproject.
+    pub pinned: Option<Oid>,
+    pub repo: Repository,
+    /// Why the submodule isn't analyzed from its pinned commit, if it isn't.
+    pub warning: Option<String>,
+}
+
+impl SubmoduleRepo {
+    /// History to analyze for this submodule. Walks from the pinned commit when
+    /// it's available locally, otherwise falls back to the submodule's `HEAD`.
+    pub fn history(&self) -> HistorySource<'_> {
+        HistorySource {
+            repo: &self.repo,
+            start: self
+                .pinned
+                .filter(|oid| self.repo.find_commit(*oid).is_ok()),
+            name: Some(&self.name),
+        }
+    }
+}
+
+/// A submodule of the analyzed commit that could not be opened, e.g. because
+/// it isn't checked out.
+pub struct SkippedSubmodule {
+    pub name: String,
+    pub path: PathBuf,
+    pub pinned: Option<Oid>,
+    pub reason: String,
+}
+
+/// Submodules found by [`open_submodules`].
+#[derive(Default)]
+pub struct Submodules {
+    pub repos: Vec<SubmoduleRepo>,
+    pub skipped: Vec<SkippedSubmodule>,
+}
+
+/// Per-submodule breakdown of the contributors.
+pub struct SubmoduleReport {
+    pub name: String,
+    pub path: PathBuf,
+    pub pinned: Option<Oid>,
+    pub users: Vec<UserInfo>,
+    /// Why the submodule was left out or not analyzed from its pinned commit.
+    pub warning: Option<String>,
+}
+
+/// Opens every initialized submodule of `repo` that is part of the commit
+/// `tip`, including nested ones, pinned at the commits `tip` records.
+/// Submodules that can't be opened are listed in [`Submodules::skipped`].
+pub fn open_submodules(repo: &Repository, tip: Oid) -> Result<Submodules> {
+    let tree = repo.find_commit(tip)?.tree()?;
+    let mut submodules = Submodules::default();
+    collect_submodules(repo, Some(&tree), None, Path::new(""), &mut submodules)?;
+    Ok(submodules)
+}
+
+/// Collects the submodules of `repo` found in `tree`, the tree of the commit
+/// its history is analyzed from, if any.
+fn collect_submodules(
+    repo: &Repository,
+    tree: Option<&Tree>,
+    parent_name: Option<&str>,
+    parent_path: &Path,
+    submodules: &mut Submodules,
+) -> Result<()> {
+    for submodule in repo.submodules()? {
+        let pinned = match tree {
+            Some(tree) => match tree.get_path(submodule.path()) {
+                Ok(entry) => Some(entry.id()),
+                // Not part of the analyzed commit
+                Err(_) => continue,
+            },
+            None => None,
+        };
+        let name = submodule.name().unwrap_or("unknown");
+        let name = match parent_name {
+            Some(parent) => format!("{parent}/{name}"),
+            None => name.to_string(),
+        };
+        let path = parent_path.join(submodule.path());
+
+        let sub_repo = match submodule.open() {
+            Ok(sub_repo) => sub_repo,
+            Err(err) => {
+                submodules.skipped.push(SkippedSubmodule {
+                    name,
+                    path,
+                    pinned,
+                    reason: format!("not analyzed, it could not be opened: {}", err.message()),
+                });
+                continue;
+            }
+        };
+
+        let pinned_available = pinned.is_some_and(|oid| sub_repo.find_commit(oid).is_ok());
+        let warning = pinned
+            .filter(|_| !pinned_available)
+            .map(|oid| format!("pinned commit {oid} is not available, analyzed from its HEAD"));
+        {
+            // Nested submodules are pinned by the commit this one is analyzed from
+            let start = match pinned.filter(|_| pinned_available) {
+                Some(oid) => Some(sub_repo.find_commit(oid)?),
+                None => sub_repo.head().and_then(|head| head.peel_to_commit()).ok(),
+            };
+            let sub_tree = start.map(|commit| commit.tree()).transpose()?;
+            collect_submodules(&sub_repo, sub_tree.as_ref(), Some(&name), &path, submodules)?;
+        }
+        submodules.repos.push(SubmoduleRepo {
+            name,
+            path,
+            pinned,
+            repo: sub_repo,
+            warning,
+        });
+    }
+
+    Ok(())
+}
+
+/// Contributors of each submodule on its own, from a `report` of a query
+/// that included the [`history`](SubmoduleRepo::history) of every submodule.
+/// The skipped submodules are listed without contributors.
+pub fn submodule_reports(submodules: &Submodules, report: &Report) -> Vec<SubmoduleReport> {
+    let opened = submodules.repos.iter().map(|submodule| SubmoduleReport {
+        name: submodule.name.clone(),
+        path: submodule.path.clone(),
+        pinned: submodule.pinned,
+        users: report
+            .sources
+            .get(&submodule.name)
+            .cloned()
+            .unwrap_or_default(),
+        warning: submodule.warning.clone(),
+    });
+    let skipped = submodules.skipped.iter().map(|submodule| SubmoduleReport {
+        name: submodule.name.clone(),
+        path: submodule.path.clone(),
+        pinned: submodule.pinned,
+        users: Vec::new(),
+        warning: Some(submodule.reason.clone()),
+    });
+    opened.chain(skipped).collect()
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use chrono::{Days, Months, prelude::*};
+
+use crate::repo_stats::RepoStats;
+
+/// Size of the periods a time series is grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Year,
+}
+
+impl Granularity {
+    pub fn next(self) -> Self {
+        match self {
+            Granularity::Day => Granularity::Week,
+            Granularity::Week => Granularity::Month,
+            Granularity::Month => Granularity::Year,
+            Granularity::Year => Granularity::Day,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+            Granularity::Year => "year",
+        }
+    }
+
+    /// First day of the period `date` belongs to. Weeks start on Monday.
+    pub fn period_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Granularity::Month => date.with_day(1).unwrap_or(date),
+            Granularity::Year => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+
+    /// First day of the period following the one starting at `start`.
+    pub fn next_period(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => start + Days::new(1),
+            Granularity::Week => start + Days::new(7),
+            Granularity::Month => start + Months::new(1),
+            Granularity::Year => start + Months::new(12),
+        }
+    }
+
+    /// Short label of the period starting at `start`.
+    pub fn format(self, start: NaiveDate) -> String {
+        match self {
+            Granularity::Day | Granularity::Week => start.format("%Y-%m-%d").to_string(),
+            Granularity::Month => start.format("%Y-%m").to_string(),
+            Granularity::Year => start.format("%Y").to_string(),
+        }
+    }
+}
+
+/// Sums `values` per period, in chronological order. Every period between the
+/// ones of `from` and `to` is included, with zero when there is no value.
+/// Values outside that range are ignored.
+pub fn bucket(
+    values: impl IntoIterator<Item = (NaiveDate, usize)>,
+    granularity: Granularity,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(NaiveDate, usize)> {
+    let mut periods = BTreeMap::new();
+    let last = granularity.period_start(to);
+    let mut period = granularity.period_start(from);
+    while period <= last {
+        periods.insert(period, 0);
+        period = granularity.next_period(period);
+    }
+
+    for (date, value) in values {
+        if let Some(total) = periods.get_mut(&granularity.period_start(date)) {
+            *total += value;
+        }
+    }
+    periods.into_iter().collect()
+}
+
+/// Commits and lines changed per period, from the first commit of a
+/// repository to its last one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeSeries {
+    pub granularity: Granularity,
+    /// Number of commits per period, keyed by the first day of the period.
+    pub commits: Vec<(NaiveDate, usize)>,
+    /// Lines added plus removed per period, keyed by the first day of the
+    /// period.
+    pub line_changes: Vec<(NaiveDate, usize)>,
+}
+
+impl TimeSeries {
+    pub fn new(stats: &RepoStats, granularity: Granularity) -> Self {
+        let (Some(from), Some(to)) = (
+            stats.commits_per_day.keys().next().copied(),
+            stats.commits_per_day.keys().next_back().copied(),
+        ) else {
+            return TimeSeries {
+                granularity,
+                ..TimeSeries::default()
+            };
+        };
+        let series = |per_day: &BTreeMap<NaiveDate, usize>| {
+            bucket(per_day.iter().map(|(d, v)| (*d, *v)), granularity, from, to)
+        };
+        TimeSeries {
+            granularity,
+            commits: series(&stats.commits_per_day),
+            line_changes: series(&stats.line_changes_per_day),
+        }
+    }
+}
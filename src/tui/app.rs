@@ -1,28 +1,434 @@
-use crate::users::UserInfo;
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use git2::Oid;
+
+use ratatui::layout::{Position, Rect};
+use regex::{Regex, RegexBuilder};
+use uuid::Uuid;
+
+use crate::analysis::{Analysis, AnalysisEvent, CommitRecord, Progress};
+use crate::churn::ChurnOptions;
+use crate::export::{Export, UserSummary};
+use crate::query::{Report, StatsQuery};
+use crate::repo_stats::RepoStats;
+use crate::repository::{self, ShallowBoundary};
+use crate::timeseries::Granularity;
+use crate::users::{StatsAccumulator, UserInfo};
+use crate::watch::RefWatcher;
+
+use super::keymap::Keymap;
+use super::theme::Theme;
+
+/// Maximum number of analysis events applied per tick, so a fast worker can't
+/// starve the event loop.
+const MAX_EVENTS_PER_TICK: usize = 10_000;
+
+/// How often the users are recomputed while an analysis adds commits. Each
+/// refresh recomputes the stats of every user who got new commits.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    /// The analysis is counting the commits to process.
+    Counting,
+    Loading(Progress),
+    Done,
+    Failed(String),
+}
+
+/// Tab shown in the main area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Authors,
+    Timeline,
+    Files,
+    Summary,
+}
+
+impl View {
+    pub const ALL: [View; 4] = [View::Authors, View::Timeline, View::Files, View::Summary];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            View::Authors => "Authors",
+            View::Timeline => "Timeline",
+            View::Files => "Files",
+            View::Summary => "Summary",
+        }
+    }
+
+    pub fn index(self) -> usize {
+        View::ALL.iter().position(|v| *v == self).unwrap_or(0)
+    }
+
+    pub fn next(self) -> Self {
+        View::ALL[(self.index() + 1) % View::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        View::ALL[(self.index() + View::ALL.len() - 1) % View::ALL.len()]
+    }
+}
+
+/// Value plotted in the activity chart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartMetric {
+    Commits,
+    Lines,
+}
+
+/// Key the user list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Commits,
+    LinesAdded,
+    LinesRemoved,
+    NetLines,
+    FirstCommit,
+    LastCommit,
+    Name,
+}
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Commits => SortKey::LinesAdded,
+            SortKey::LinesAdded => SortKey::LinesRemoved,
+            SortKey::LinesRemoved => SortKey::NetLines,
+            SortKey::NetLines => SortKey::FirstCommit,
+            SortKey::FirstCommit => SortKey::LastCommit,
+            SortKey::LastCommit => SortKey::Name,
+            SortKey::Name => SortKey::Commits,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Commits => "commits",
+            SortKey::LinesAdded => "lines added",
+            SortKey::LinesRemoved => "lines removed",
+            SortKey::NetLines => "net lines",
+            SortKey::FirstCommit => "first commit",
+            SortKey::LastCommit => "last commit",
+            SortKey::Name => "name",
+        }
+    }
+
+    /// Compares two users in the natural order of this key: biggest numbers and
+    /// most recent activity first, oldest first commit first, names A to Z.
+    pub fn compare(self, a: &UserInfo, b: &UserInfo) -> Ordering {
+        match self {
+            SortKey::Commits => b.stats.total_commits.cmp(&a.stats.total_commits),
+            SortKey::LinesAdded => b.stats.total_lines_added.cmp(&a.stats.total_lines_added),
+            SortKey::LinesRemoved => b
+                .stats
+                .total_lines_removed
+                .cmp(&a.stats.total_lines_removed),
+            SortKey::NetLines => b.stats.net_lines().cmp(&a.stats.net_lines()),
+            SortKey::FirstCommit => a.stats.first_commit.cmp(&b.stats.first_commit),
+            SortKey::LastCommit => b.stats.last_commit.cmp(&a.stats.last_commit),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        }
+    }
+}
+
+/// Screen position of a table, recorded while rendering to map mouse events
+/// back to rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableArea {
+    pub area: Rect,
+    /// Index of the first row shown.
+    pub offset: usize,
+    pub has_header: bool,
+}
+
+impl TableArea {
+    /// Index of the row at line `y` of the screen, if any.
+    pub fn row_at(&self, y: u16) -> Option<usize> {
+        // Skip the top border and the header
+        let first_row_y = self.area.y + 1 + u16::from(self.has_header);
+        let last_y = self.area.bottom().saturating_sub(1);
+        (y >= first_row_y && y < last_y).then(|| self.offset + (y - first_row_y) as usize)
+    }
+}
+
+/// Screen position and data of a bar chart, recorded while rendering so hovered
+/// bars can show their value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BarChartArea {
+    pub title: &'static str,
+    pub area: Rect,
+    pub bar_width: u16,
+    pub bar_gap: u16,
+    pub bars: Vec<(String, u64)>,
+}
+
+impl BarChartArea {
+    /// Index of the bar at column `x`, if any.
+    pub fn bar_at(&self, x: u16) -> Option<usize> {
+        // Skip the left border
+        let offset = x.checked_sub(self.area.x + 1)?;
+        let slot = self.bar_width + self.bar_gap;
+        let index = (offset / slot) as usize;
+        (offset % slot < self.bar_width && index < self.bars.len()).then_some(index)
+    }
+}
+
+/// Areas of the last rendered frame that react to the mouse.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScreenAreas {
+    /// Columns covered by each tab title.
+    pub tabs: Vec<Rect>,
+    pub users: Option<TableArea>,
+    pub commits: Option<TableArea>,
+    pub commit_files: Option<Rect>,
+    pub files: Option<TableArea>,
+    pub bar_charts: Vec<BarChartArea>,
+}
+
+/// Bar under the mouse cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoveredBar {
+    pub chart: &'static str,
+    pub label: String,
+    pub value: u64,
+}
+
+/// Drill-down into the commits of the selected user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitView {
+    /// Index of the selected commit in the user's commits.
+    pub selected: usize,
+    /// Whether the changed files of the selected commit are shown.
+    pub show_files: bool,
+    pub file_scroll: usize,
+}
+
+/// What an export writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    /// The stats of the selected user.
+    User,
+    /// The compared users.
+    Comparison,
+    /// The users matching the filter, in display order.
+    Users,
+}
+
+impl ExportTarget {
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportTarget::User => "user stats",
+            ExportTarget::Comparison => "comparison",
+            ExportTarget::Users => "user list",
+        }
+    }
+
+    fn default_path(self) -> &'static str {
+        match self {
+            ExportTarget::User => "git-stats-user.md",
+            ExportTarget::Comparison => "git-stats-comparison.md",
+            ExportTarget::Users => "git-stats-users.md",
+        }
+    }
+}
+
+/// Export waiting for the path to write to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportPrompt {
+    pub target: ExportTarget,
+    pub path: String,
+}
+
+/// Message shown on the status line until the next key press.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusMessage {
+    pub text: String,
+    pub is_error: bool,
+}
+
+/// Where the stats were loaded from, so they can be updated when the refs move.
+struct Source {
+    repo_path: PathBuf,
+    revspec: String,
+    churn: ChurnOptions,
+    recurse_submodules: bool,
+    /// Commit the stats are up to date with.
+    tip: Option<Oid>,
+    /// Commit the running analysis goes up to.
+    pending_tip: Option<Oid>,
+    watcher: Option<RefWatcher>,
+    /// Whether the refs moved since the last analysis was started.
+    stale: bool,
+}
 
 pub struct App {
-    pub users: Vec<UserInfo>,
+    pub view: View,
+    /// Set when the repository is a shallow clone, so the stats don't cover
+    /// the whole history.
+    pub partial: Option<ShallowBoundary>,
     pub selected_user_index: Option<usize>,
     pub should_quit: bool,
-    pub focus_users: bool, // true = focus on user list, false = focus on stats
+    pub load_state: LoadState,
+    pub sort_key: SortKey,
+    pub sort_reversed: bool,
+    /// Whether the search input is receiving keys.
+    pub search_mode: bool,
+    pub filter: String,
+    /// Indices in [`App::users`] of the users shown in the list: the ones with
+    /// enough commits that match the filter, in display order.
+    pub visible_users: Vec<usize>,
+    filter_regex: Option<Regex>,
+    pub commit_view: Option<CommitView>,
+    /// Users marked for comparison, in the order they were marked.
+    pub marked_users: Vec<Uuid>,
+    /// Whether the comparison of the marked users is shown.
+    pub comparing: bool,
+    pub granularity: Granularity,
+    pub chart_metric: ChartMetric,
+    pub areas: ScreenAreas,
+    pub hovered_bar: Option<HoveredBar>,
+    /// Selected row of the Files view.
+    pub selected_file_index: usize,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    /// Whether the list of keys is shown.
+    pub show_help: bool,
+    pub export_prompt: Option<ExportPrompt>,
+    pub status: Option<StatusMessage>,
+    analysis: Option<Analysis>,
+    source: Option<Source>,
+    /// Owner of the users and repository stats shown.
+    accumulator: StatsAccumulator,
+    /// Stats of a full reload, shown once it finishes so the previous ones
+    /// stay on screen meanwhile.
+    pending: Option<StatsAccumulator>,
+    last_refresh: Option<Instant>,
 }
 
 impl App {
+    /// Creates an app showing `users`. The repository stats are computed from
+    /// their commits, and users with too few commits are then left out.
     pub fn new(users: Vec<UserInfo>) -> Self {
-        let selected = if !users.is_empty() { Some(0) } else { None };
-        App {
-            users,
-            selected_user_index: selected,
+        let repo_stats = RepoStats::from_users(&users);
+        App::from_users(users, repo_stats)
+    }
+
+    fn from_users(users: Vec<UserInfo>, repo_stats: RepoStats) -> Self {
+        let mut app = App {
+            view: View::Authors,
+            partial: None,
+            selected_user_index: None,
             should_quit: false,
-            focus_users: true,
+            load_state: LoadState::Done,
+            sort_key: SortKey::Commits,
+            sort_reversed: false,
+            search_mode: false,
+            filter: String::new(),
+            visible_users: Vec::new(),
+            filter_regex: None,
+            commit_view: None,
+            marked_users: Vec::new(),
+            comparing: false,
+            granularity: Granularity::default(),
+            chart_metric: ChartMetric::Commits,
+            areas: ScreenAreas::default(),
+            hovered_bar: None,
+            selected_file_index: 0,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            show_help: false,
+            export_prompt: None,
+            status: None,
+            analysis: None,
+            source: None,
+            accumulator: StatsAccumulator::from_users(users, repo_stats),
+            pending: None,
+            last_refresh: None,
+        };
+        app.sort_users();
+        app
+    }
+
+    /// Creates an app showing the users and repository stats of `report`.
+    pub fn from_report(report: Report) -> Self {
+        let mut app = App::from_users(report.users, report.repo_stats);
+        for (name, users) in report.sources {
+            app.accumulator = app.accumulator.with_source(name, users);
         }
+        app.partial = report.partial;
+        app
+    }
+
+    /// Creates an app that fills in the users while `analysis` runs.
+    pub fn with_analysis(analysis: Analysis) -> Self {
+        App {
+            load_state: LoadState::Counting,
+            source: Some(Source {
+                repo_path: analysis.repo_path().to_path_buf(),
+                revspec: analysis.revspec().to_string(),
+                churn: analysis.churn().clone(),
+                recurse_submodules: analysis.recurses_submodules(),
+                tip: None,
+                pending_tip: None,
+                watcher: None,
+                stale: false,
+            }),
+            analysis: Some(analysis),
+            ..App::new(Vec::new())
+        }
+    }
+
+    /// Analyzes new commits whenever `watcher` sees the refs move. Only apps
+    /// created with [`App::with_analysis`] can reload their stats.
+    pub fn with_ref_watcher(mut self, watcher: RefWatcher) -> Self {
+        if let Some(source) = self.source.as_mut() {
+            source.watcher = Some(watcher);
+        }
+        self
+    }
+
+    /// Draws the app with `theme` instead of the default one.
+    pub fn with_theme(self, theme: Theme) -> Self {
+        App { theme, ..self }
+    }
+
+    /// Maps keys to actions with `keymap` instead of the default one.
+    pub fn with_keymap(self, keymap: Keymap) -> Self {
+        App { keymap, ..self }
+    }
+
+    /// Every user seen so far, including the ones with too few commits to be
+    /// listed. See [`App::visible_users`].
+    pub fn users(&self) -> &[UserInfo] {
+        self.accumulator.current_users()
+    }
+
+    pub fn repo_stats(&self) -> &RepoStats {
+        self.accumulator.repo_stats()
+    }
+
+    /// Users of each submodule, by name, when they are analyzed too.
+    pub fn submodules(&self) -> impl Iterator<Item = (&str, &[UserInfo])> {
+        self.accumulator.sources()
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(self.load_state, LoadState::Counting | LoadState::Loading(_))
     }
 
     pub fn next(&mut self) {
-        if !self.users.is_empty() {
-            let i = match self.selected_user_index {
+        if !self.visible_users.is_empty() {
+            let i = match self.selected_visible_index() {
                 Some(i) => {
-                    if i >= self.users.len() - 1 {
+                    if i >= self.visible_users.len() - 1 {
                         0
                     } else {
                         i + 1
@@ -30,39 +436,569 @@ impl App {
                 }
                 None => 0,
             };
-            self.selected_user_index = Some(i);
+            self.selected_user_index = Some(self.visible_users[i]);
         }
     }
 
     pub fn previous(&mut self) {
-        if !self.users.is_empty() {
-            let i = match self.selected_user_index {
+        if !self.visible_users.is_empty() {
+            let i = match self.selected_visible_index() {
                 Some(i) => {
                     if i == 0 {
-                        self.users.len() - 1
+                        self.visible_users.len() - 1
                     } else {
                         i - 1
                     }
                 }
                 None => 0,
             };
-            self.selected_user_index = Some(i);
+            self.selected_user_index = Some(self.visible_users[i]);
+        }
+    }
+
+    /// Position of the selected user among the visible ones.
+    pub fn selected_visible_index(&self) -> Option<usize> {
+        self.selected_user_index
+            .and_then(|selected| self.visible_users.iter().position(|&i| i == selected))
+    }
+
+    pub fn start_search(&mut self) {
+        self.search_mode = true;
+    }
+
+    /// Leaves the search input, keeping the filter.
+    pub fn finish_search(&mut self) {
+        self.search_mode = false;
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search_mode = false;
+        self.filter.clear();
+        self.apply_filter();
+    }
+
+    pub fn push_search_char(&mut self, c: char) {
+        self.filter.push(c);
+        self.apply_filter();
+    }
+
+    pub fn pop_search_char(&mut self) {
+        self.filter.pop();
+        self.apply_filter();
+    }
+
+    /// Regex the users are filtered with. Filters that aren't valid regular
+    /// expressions are matched literally.
+    pub fn filter_regex(&self) -> Option<&Regex> {
+        self.filter_regex.as_ref()
+    }
+
+    /// Recomputes the visible users, keeping the selection if it still
+    /// matches, otherwise selecting the first match.
+    fn apply_filter(&mut self) {
+        self.filter_regex = if self.filter.is_empty() {
+            None
+        } else {
+            RegexBuilder::new(&self.filter)
+                .case_insensitive(true)
+                .build()
+                .or_else(|_| {
+                    RegexBuilder::new(&regex::escape(&self.filter))
+                        .case_insensitive(true)
+                        .build()
+                })
+                .ok()
+        };
+        self.sort_users();
+    }
+
+    pub fn next_view(&mut self) {
+        self.view = self.view.next();
+    }
+
+    pub fn previous_view(&mut self) {
+        self.view = self.view.previous();
+    }
+
+    pub fn cycle_granularity(&mut self) {
+        self.granularity = self.granularity.next();
+    }
+
+    pub fn toggle_chart_metric(&mut self) {
+        self.chart_metric = match self.chart_metric {
+            ChartMetric::Commits => ChartMetric::Lines,
+            ChartMetric::Lines => ChartMetric::Commits,
+        };
+    }
+
+    pub fn next_file(&mut self) {
+        if self.selected_file_index + 1 < self.repo_stats().files.len() {
+            self.selected_file_index += 1;
         }
     }
 
-    pub fn toggle_focus(&mut self) {
-        self.focus_users = !self.focus_users;
+    pub fn previous_file(&mut self) {
+        self.selected_file_index = self.selected_file_index.saturating_sub(1);
+    }
+
+    pub fn cycle_sort_key(&mut self) {
+        self.sort_key = self.sort_key.next();
+        self.sort_users();
+    }
+
+    pub fn reverse_sort(&mut self) {
+        self.sort_reversed = !self.sort_reversed;
+        self.sort_users();
+    }
+
+    /// Lists the users matching the filter, sorted by the active sort key.
+    /// The selection stays on the same user if they are still listed,
+    /// otherwise moves to the first one.
+    pub fn sort_users(&mut self) {
+        let users = self.accumulator.current_users();
+        let regex = self.filter_regex.as_ref();
+        self.visible_users = users
+            .iter()
+            .enumerate()
+            .filter(|(_, u)| is_significant(u))
+            .filter(|(_, u)| regex.is_none_or(|r| r.is_match(&u.name) || r.is_match(&u.email)))
+            .map(|(i, _)| i)
+            .collect();
+
+        let (key, reversed) = (self.sort_key, self.sort_reversed);
+        self.visible_users.sort_by(|&a, &b| {
+            let (a, b) = (&users[a], &users[b]);
+            // Fall back to the commits, so ties keep a stable order
+            let ordering = key
+                .compare(a, b)
+                .then_with(|| SortKey::Commits.compare(a, b))
+                .then_with(|| SortKey::Name.compare(a, b));
+            if reversed {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        if self.selected_visible_index().is_none() {
+            self.selected_user_index = self.visible_users.first().copied();
+        }
     }
 
     pub fn selected_user(&self) -> Option<&UserInfo> {
-        self.selected_user_index.map(|i| &self.users[i])
+        self.selected_user_index.map(|i| &self.users()[i])
+    }
+
+    /// Marks the selected user for comparison, or unmarks it.
+    pub fn toggle_mark(&mut self) {
+        let Some(id) = self.selected_user().map(|u| u.id) else {
+            return;
+        };
+        match self.marked_users.iter().position(|marked| *marked == id) {
+            Some(i) => {
+                self.marked_users.remove(i);
+            }
+            None => self.marked_users.push(id),
+        }
+    }
+
+    pub fn is_marked(&self, user: &UserInfo) -> bool {
+        self.marked_users.contains(&user.id)
+    }
+
+    /// Marked users that are still in the list, in the order they were marked.
+    pub fn compared_users(&self) -> Vec<&UserInfo> {
+        self.marked_users
+            .iter()
+            .filter_map(|id| self.users().iter().find(|u| u.id == *id))
+            .collect()
+    }
+
+    /// Opens the comparison view, if at least two users are marked.
+    pub fn open_comparison(&mut self) {
+        if self.compared_users().len() >= 2 {
+            self.comparing = true;
+        }
+    }
+
+    pub fn close_comparison(&mut self) {
+        self.comparing = false;
+    }
+
+    /// Asks for the path to export `target` to, if there is anything to export.
+    pub fn start_export(&mut self, target: ExportTarget) {
+        if self.export_data(target).is_some() {
+            self.export_prompt = Some(ExportPrompt {
+                target,
+                path: target.default_path().to_string(),
+            });
+        }
+    }
+
+    pub fn push_export_char(&mut self, c: char) {
+        if let Some(prompt) = self.export_prompt.as_mut() {
+            prompt.path.push(c);
+        }
+    }
+
+    pub fn pop_export_char(&mut self) {
+        if let Some(prompt) = self.export_prompt.as_mut() {
+            prompt.path.pop();
+        }
+    }
+
+    pub fn cancel_export(&mut self) {
+        self.export_prompt = None;
+    }
+
+    /// Writes the export to the entered path, in the format matching its
+    /// extension, and reports the outcome on the status line. The prompt only
+    /// closes once the file is written, so the path can be fixed after an
+    /// error. An existing file is never overwritten.
+    pub fn finish_export(&mut self) {
+        let Some(prompt) = self.export_prompt.as_ref() else {
+            return;
+        };
+        let target = prompt.target;
+        let path = PathBuf::from(prompt.path.trim());
+        if path.exists() {
+            self.status = Some(StatusMessage {
+                text: format!("{} already exists, enter another path", path.display()),
+                is_error: true,
+            });
+            return;
+        }
+        let result = match self.export_data(target) {
+            Some(export) => export.save(&path),
+            None => Err(anyhow!("Nothing to export")),
+        };
+        self.status = Some(match result {
+            Ok(()) => {
+                self.export_prompt = None;
+                StatusMessage {
+                    text: format!("Exported {} to {}", target.label(), path.display()),
+                    is_error: false,
+                }
+            }
+            Err(err) => StatusMessage {
+                text: format!("Export failed: {:#}", err),
+                is_error: true,
+            },
+        });
+    }
+
+    fn export_data(&self, target: ExportTarget) -> Option<Export> {
+        let total_commits = self.repo_stats().total_commits;
+        match target {
+            ExportTarget::User => self
+                .selected_user()
+                .map(|u| Export::User(UserSummary::new(u, total_commits))),
+            ExportTarget::Comparison => {
+                let users = self.compared_users();
+                (self.comparing && !users.is_empty()).then(|| {
+                    Export::Comparison(
+                        users
+                            .iter()
+                            .map(|u| UserSummary::new(u, total_commits))
+                            .collect(),
+                    )
+                })
+            }
+            ExportTarget::Users => Some(Export::Users(
+                self.visible_users
+                    .iter()
+                    .map(|&i| UserSummary::new(&self.users()[i], total_commits))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Opens the commits of the selected user.
+    pub fn open_commits(&mut self) {
+        if self
+            .selected_user()
+            .is_some_and(|u| !u.stats.commits.is_empty())
+        {
+            self.commit_view = Some(CommitView::default());
+        }
+    }
+
+    /// Shows the changed files of the selected commit.
+    pub fn open_commit_files(&mut self) {
+        if let Some(view) = self.commit_view.as_mut() {
+            view.show_files = true;
+            view.file_scroll = 0;
+        }
+    }
+
+    /// Goes back one level: from the files to the commits, and from the
+    /// commits to the users.
+    pub fn close_commit_view(&mut self) {
+        match self.commit_view.as_mut() {
+            Some(view) if view.show_files => view.show_files = false,
+            _ => self.commit_view = None,
+        }
+    }
+
+    pub fn selected_commit(&self) -> Option<&CommitRecord> {
+        let view = self.commit_view.as_ref()?;
+        self.selected_user()?.stats.commits.get(view.selected)
+    }
+
+    /// Moves down the commit list, or scrolls the files when they are shown.
+    pub fn next_commit(&mut self) {
+        let commit_count = self.selected_user().map_or(0, |u| u.stats.commits.len());
+        let file_count = self
+            .selected_commit()
+            .map_or(0, |c| c.files.len() + c.excluded_files.len());
+        let Some(view) = self.commit_view.as_mut() else {
+            return;
+        };
+        if view.show_files {
+            view.file_scroll = (view.file_scroll + 1).min(file_count.saturating_sub(1));
+        } else if view.selected + 1 < commit_count {
+            view.selected += 1;
+        }
+    }
+
+    /// Moves up the commit list, or scrolls the files when they are shown.
+    pub fn previous_commit(&mut self) {
+        let Some(view) = self.commit_view.as_mut() else {
+            return;
+        };
+        if view.show_files {
+            view.file_scroll = view.file_scroll.saturating_sub(1);
+        } else {
+            view.selected = view.selected.saturating_sub(1);
+        }
+    }
+
+    /// Selects the user shown at row `row` of the users table.
+    pub fn select_visible_user(&mut self, row: usize) {
+        if let Some(&i) = self.visible_users.get(row) {
+            if self.selected_user_index != Some(i) && self.commit_view.is_some() {
+                // Show the commits of the new user from the top
+                self.commit_view = Some(CommitView::default());
+            }
+            self.selected_user_index = Some(i);
+        }
+    }
+
+    pub fn select_commit(&mut self, index: usize) {
+        let commit_count = self.selected_user().map_or(0, |u| u.stats.commits.len());
+        if let Some(view) = self.commit_view.as_mut()
+            && index < commit_count
+        {
+            view.selected = index;
+            view.file_scroll = 0;
+        }
+    }
+
+    pub fn select_file(&mut self, index: usize) {
+        if index < self.repo_stats().files.len() {
+            self.selected_file_index = index;
+        }
+    }
+
+    /// Updates the hovered bar for a mouse cursor at `position`.
+    pub fn hover(&mut self, position: Position) {
+        self.hovered_bar = self
+            .areas
+            .bar_charts
+            .iter()
+            .filter(|chart| chart.area.contains(position))
+            .find_map(|chart| {
+                let (label, value) = chart.bars.get(chart.bar_at(position.x)?)?;
+                Some(HoveredBar {
+                    chart: chart.title,
+                    label: label.clone(),
+                    value: *value,
+                })
+            });
     }
 
     pub fn on_tick(&mut self) {
-        // Update any dynamic components if needed
+        if let Some(source) = self.source.as_mut() {
+            if source.watcher.as_ref().is_some_and(RefWatcher::changed) {
+                source.stale = true;
+            }
+            // Wait for the running analysis, the refs may move again meanwhile
+            if source.stale && self.analysis.is_none() {
+                source.stale = false;
+                self.reload();
+            }
+        }
+
+        if let Some(analysis) = self.analysis.as_mut() {
+            let mut finished = false;
+            for _ in 0..MAX_EVENTS_PER_TICK {
+                let Some(event) = analysis.try_next() else {
+                    break;
+                };
+                match event {
+                    AnalysisEvent::Started {
+                        total_commits,
+                        tip,
+                        partial,
+                    } => {
+                        self.partial = partial;
+                        self.load_state = LoadState::Loading(Progress {
+                            processed: 0,
+                            total: total_commits,
+                        });
+                        if let Some(source) = self.source.as_mut() {
+                            source.pending_tip = Some(tip);
+                        }
+                    }
+                    AnalysisEvent::Commit(record) => {
+                        self.pending
+                            .as_mut()
+                            .unwrap_or(&mut self.accumulator)
+                            .add(record);
+                    }
+                    AnalysisEvent::Progress(progress) => {
+                        self.load_state = LoadState::Loading(progress);
+                    }
+                    AnalysisEvent::Finished => {
+                        self.load_state = LoadState::Done;
+                        if let Some(source) = self.source.as_mut() {
+                            source.tip = source.pending_tip.take();
+                        }
+                        finished = true;
+                    }
+                    AnalysisEvent::Cancelled => {
+                        self.load_state = LoadState::Done;
+                        self.pending = None;
+                    }
+                    AnalysisEvent::Failed(err) => {
+                        // Shown with its causes, like the CLI prints errors
+                        self.load_state =
+                            LoadState::Failed(format!("{:#}", anyhow::Error::new(err)));
+                        self.pending = None;
+                    }
+                }
+            }
+
+            if analysis.is_done() {
+                self.analysis = None;
+                // Show the final stats without waiting for the next refresh
+                self.last_refresh = None;
+            }
+            if finished && let Some(pending) = self.pending.take() {
+                self.replace_accumulator(pending);
+            }
+        }
+
+        let due = self
+            .last_refresh
+            .is_none_or(|last| last.elapsed() >= REFRESH_INTERVAL);
+        if due && self.accumulator.has_changes() {
+            self.refresh_users();
+            self.last_refresh = Some(Instant::now());
+        }
     }
-    
+
+    /// Starts analyzing the commits added since the stats were last updated,
+    /// or the whole history again if it was rewritten.
+    fn reload(&mut self) {
+        let Some(source) = self.source.as_ref() else {
+            return;
+        };
+        let since = match reload_kind(source) {
+            Ok(Reload::UpToDate) => return,
+            // The submodules have no new commits to start from, they are
+            // analyzed again with the rest
+            Ok(Reload::Since(tip)) if !source.recurse_submodules => Some(tip),
+            Ok(Reload::Since(_) | Reload::Full) => {
+                self.pending = Some(StatsAccumulator::new());
+                None
+            }
+            Err(err) => {
+                self.status = Some(StatusMessage {
+                    text: format!("Reload failed: {:#}", err),
+                    is_error: true,
+                });
+                return;
+            }
+        };
+        self.load_state = LoadState::Counting;
+        let repo_path = source.repo_path.clone();
+        let revspec = source.revspec.clone();
+        let churn = source.churn.clone();
+        self.analysis = Some(if source.recurse_submodules {
+            Analysis::spawn_recursive(repo_path, revspec, churn)
+        } else {
+            Analysis::spawn_since(repo_path, revspec, churn, since)
+        });
+    }
+
+    /// Shows the stats of a full reload. The selected and marked users are
+    /// matched by email, since the new stats give them new ids.
+    fn replace_accumulator(&mut self, accumulator: StatsAccumulator) {
+        let selected_email = self.selected_user().map(|u| u.email.clone());
+        let marked_emails: Vec<String> = self
+            .compared_users()
+            .iter()
+            .map(|u| u.email.clone())
+            .collect();
+
+        self.accumulator = accumulator;
+        let users = self.accumulator.users();
+        let find = |email: &str| users.iter().position(|u| u.email == email);
+        self.selected_user_index = selected_email.and_then(|email| find(&email));
+        self.marked_users = marked_emails
+            .iter()
+            .filter_map(|email| find(email).map(|i| users[i].id))
+            .collect();
+        if self.commit_view.is_some() {
+            // The commit may be gone from the rewritten history
+            self.commit_view = Some(CommitView::default());
+        }
+        self.sort_users();
+    }
+
+    /// Applies the commits accumulated since the last refresh, keeping the
+    /// selected commit.
+    fn refresh_users(&mut self) {
+        let selected_commit = self.selected_commit().map(|c| c.oid);
+        self.accumulator.update();
+        if let Some(oid) = selected_commit
+            && let Some(index) = self
+                .selected_user()
+                .and_then(|u| u.stats.commits.iter().position(|c| c.oid == oid))
+            && let Some(view) = self.commit_view.as_mut()
+        {
+            view.selected = index;
+        }
+        self.sort_users();
+    }
+
     pub fn quit(&mut self) {
         self.should_quit = true;
     }
 }
+
+/// What has to be analyzed to bring the stats of a [`Source`] up to date.
+enum Reload {
+    UpToDate,
+    /// Only the commits after the given one.
+    Since(Oid),
+    /// The whole history, it was rewritten or never fully analyzed.
+    Full,
+}
+
+fn reload_kind(source: &Source) -> anyhow::Result<Reload> {
+    // Resolved like the analysis does, so ranges work too
+    let repo = repository::open(&source.repo_path)?;
+    let current = StatsQuery::new(&repo).revspec(&source.revspec).tip()?;
+    Ok(match source.tip {
+        Some(tip) if tip == current => Reload::UpToDate,
+        Some(tip) if repo.graph_descendant_of(current, tip)? => Reload::Since(tip),
+        _ => Reload::Full,
+    })
+}
+
+/// Whether `user` has enough commits to be listed.
+fn is_significant(user: &UserInfo) -> bool {
+    !user.has_not_stats() && user.stats.total_commits > 10
+}
@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+
+/// Where the TUI gets its input events from.
+pub trait EventSource {
+    /// Waits up to `timeout` for the next event. Returns `None` if there was
+    /// none in time.
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Event>>;
+}
+
+/// Events of the terminal, read with crossterm.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        if event::poll(timeout)? {
+            Ok(Some(event::read()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A fixed list of events, returned right away one after the other. Running
+/// out of events is an error, so a script that never quits can't hang.
+pub struct ScriptedEvents {
+    events: VecDeque<Event>,
+}
+
+impl ScriptedEvents {
+    pub fn new(events: impl IntoIterator<Item = Event>) -> Self {
+        ScriptedEvents {
+            events: events.into_iter().collect(),
+        }
+    }
+
+    /// A key press for every character of `keys`.
+    pub fn keys(keys: &str) -> Self {
+        ScriptedEvents::new(
+            keys.chars()
+                .map(|c| Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))),
+        )
+    }
+
+    /// Adds `event` after the ones already scripted.
+    pub fn then(mut self, event: Event) -> Self {
+        self.events.push_back(event);
+        self
+    }
+
+    /// Adds a key press after the events already scripted.
+    pub fn then_key(self, code: KeyCode) -> Self {
+        self.then(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn next_event(&mut self, _timeout: Duration) -> Result<Option<Event>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => bail!("Ran out of scripted events before the app quit"),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something the user can do with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    NextView,
+    PreviousView,
+    ShowAuthors,
+    ShowTimeline,
+    ShowFiles,
+    ShowSummary,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    Open,
+    Back,
+    Sort,
+    ReverseSort,
+    Search,
+    Granularity,
+    Metric,
+    Mark,
+    Compare,
+    Export,
+    ExportUser,
+}
+
+impl Action {
+    pub const ALL: [Action; 23] = [
+        Action::Quit,
+        Action::Help,
+        Action::NextView,
+        Action::PreviousView,
+        Action::ShowAuthors,
+        Action::ShowTimeline,
+        Action::ShowFiles,
+        Action::ShowSummary,
+        Action::Down,
+        Action::Up,
+        Action::Top,
+        Action::Bottom,
+        Action::Open,
+        Action::Back,
+        Action::Sort,
+        Action::ReverseSort,
+        Action::Search,
+        Action::Granularity,
+        Action::Metric,
+        Action::Mark,
+        Action::Compare,
+        Action::Export,
+        Action::ExportUser,
+    ];
+
+    /// Name of the action in the `[keys]` table of the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Help => "help",
+            Action::NextView => "next-view",
+            Action::PreviousView => "previous-view",
+            Action::ShowAuthors => "show-authors",
+            Action::ShowTimeline => "show-timeline",
+            Action::ShowFiles => "show-files",
+            Action::ShowSummary => "show-summary",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::Open => "open",
+            Action::Back => "back",
+            Action::Sort => "sort",
+            Action::ReverseSort => "reverse-sort",
+            Action::Search => "search",
+            Action::Granularity => "granularity",
+            Action::Metric => "metric",
+            Action::Mark => "mark",
+            Action::Compare => "compare",
+            Action::Export => "export",
+            Action::ExportUser => "export-user",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Help => "Show or hide this help",
+            Action::NextView => "Next view",
+            Action::PreviousView => "Previous view",
+            Action::ShowAuthors => "Authors view",
+            Action::ShowTimeline => "Timeline view",
+            Action::ShowFiles => "Files view",
+            Action::ShowSummary => "Summary view",
+            Action::Down => "Move down",
+            Action::Up => "Move up",
+            Action::Top => "Go to the first row",
+            Action::Bottom => "Go to the last row",
+            Action::Open => "Open the commits of a user, or the files of a commit",
+            Action::Back => "Go back, clear the filter, or quit",
+            Action::Sort => "Cycle the column users are sorted by",
+            Action::ReverseSort => "Reverse the sort order",
+            Action::Search => "Filter users by name or email (regex)",
+            Action::Granularity => "Cycle the period of the activity chart",
+            Action::Metric => "Toggle the activity chart between commits and lines",
+            Action::Mark => "Mark the selected user for comparison",
+            Action::Compare => "Compare the marked users",
+            Action::Export => "Export the user list or comparison",
+            Action::ExportUser => "Export the stats of the selected user",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q", "ctrl-c"],
+            Action::Help => &["?"],
+            Action::NextView => &["tab"],
+            Action::PreviousView => &["backtab"],
+            Action::ShowAuthors => &["1"],
+            Action::ShowTimeline => &["2"],
+            Action::ShowFiles => &["3"],
+            Action::ShowSummary => &["4"],
+            Action::Down => &["down", "j"],
+            Action::Up => &["up", "k"],
+            Action::Top => &["home", "g"],
+            Action::Bottom => &["end", "G"],
+            Action::Open => &["enter"],
+            Action::Back => &["esc"],
+            Action::Sort => &["s"],
+            Action::ReverseSort => &["r"],
+            Action::Search => &["/"],
+            Action::Granularity => &["t"],
+            Action::Metric => &["m"],
+            Action::Mark => &["space"],
+            Action::Compare => &["c"],
+            Action::Export => &["e"],
+            Action::ExportUser => &["E"],
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| anyhow!("Unknown action '{}'", s))
+    }
+}
+
+/// A key with its modifiers, e.g. `ctrl-c`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key: KeyEvent) -> Self {
+        // Shift is already part of the character, `G` rather than `shift-g`
+        let modifiers = match key.code {
+            KeyCode::Char(_) | KeyCode::BackTab => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        KeyBinding {
+            code: key.code,
+            modifiers,
+        }
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = s;
+        // A lone `-` is a key, not a separator
+        while let Some((modifier, rest)) = key.split_once('-').filter(|(_, rest)| !rest.is_empty())
+        {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                _ => bail!("Unknown modifier '{}' in key '{}'", modifier, s),
+            };
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                _ => bail!("Unknown key '{}'", s),
+            },
+        };
+        Ok(KeyBinding { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "backtab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// Keys bound to each action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<KeyBinding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|key| key.parse().expect("default keys are valid"))
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default keymap, with the keys of the actions in `overrides`
+    /// replaced. Overrides map action names to key names, see
+    /// [`Action::name`].
+    pub fn with_overrides(overrides: &HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut keymap = Keymap::default();
+        for (name, keys) in overrides {
+            let action: Action = name.parse()?;
+            let keys = keys
+                .iter()
+                .map(|key| key.parse())
+                .collect::<Result<Vec<KeyBinding>>>()?;
+            keymap.bindings.insert(action, keys);
+        }
+
+        // A key bound twice would only ever trigger one of its actions
+        let mut bound: HashMap<KeyBinding, Action> = HashMap::new();
+        for action in Action::ALL {
+            for key in keymap.keys(action) {
+                if let Some(other) = bound.insert(*key, action)
+                    && other != action
+                {
+                    bail!(
+                        "Key '{}' is bound to both '{}' and '{}'",
+                        key,
+                        other.name(),
+                        action.name()
+                    );
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Action bound to `key`, if any.
+    pub fn action(&self, key: KeyEvent) -> Option<Action> {
+        let binding = KeyBinding::from(key);
+        Action::ALL
+            .into_iter()
+            .find(|action| self.keys(*action).contains(&binding))
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Keys of `action`, for display, e.g. `down, j`.
+    pub fn describe(&self, action: Action) -> String {
+        self.keys(action)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
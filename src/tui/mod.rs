@@ -1,21 +1,29 @@
 pub mod app;
+pub mod events;
+pub mod keymap;
+pub mod theme;
 pub mod ui;
 
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use app::App;
+use app::{App, ExportTarget, TableArea, View};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, prelude::CrosstermBackend};
+use events::{EventSource, TerminalEvents};
+use keymap::Action;
+use ratatui::{Terminal, layout::Position, prelude::CrosstermBackend};
 
-use crate::users::UserInfo;
-
-pub fn run_tui(users: Vec<UserInfo>) -> Result<()> {
+/// Runs the TUI until the user quits. The app may still be loading its users
+/// in the background, see [`App::with_analysis`].
+pub fn run_tui(mut app: App) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -25,8 +33,7 @@ pub fn run_tui(users: Vec<UserInfo>) -> Result<()> {
 
     // Create app and run it
     let tick_rate = Duration::from_millis(250);
-    let app = App::new(users);
-    let res = run_app(&mut terminal, app, tick_rate);
+    let res = run_app(&mut terminal, &mut app, &mut TerminalEvents, tick_rate);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -44,37 +51,207 @@ pub fn run_tui(users: Vec<UserInfo>) -> Result<()> {
     Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(
+/// Draws `app` and handles the events of `events` until the app quits.
+/// `app.on_tick` is called every `tick_rate`.
+pub fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
-    mut app: App,
+    app: &mut App,
+    events: &mut impl EventSource,
     tick_rate: Duration,
 ) -> Result<()> {
+    let mut last_tick = Instant::now();
     loop {
-        terminal.draw(|f| ui::render(f, &mut app))?;
-        event::poll(tick_rate)?;
-        handle_events(&mut app)?;
+        terminal.draw(|f| ui::render(f, app))?;
+        let timeout = tick_rate.saturating_sub(last_tick.elapsed());
+        if let Some(event) = events.next_event(timeout)? {
+            handle_event(app, event);
+        }
         if app.should_quit {
             return Ok(());
         }
+        if last_tick.elapsed() >= tick_rate {
+            app.on_tick();
+            last_tick = Instant::now();
+        }
     }
 }
 
-fn handle_events(app: &mut App) -> Result<()> {
-    let timeout = Duration::from_secs_f64(1.0 / 50.0);
-    if !event::poll(timeout)? {
-        return Ok(());
+/// Updates `app` for a single input event.
+pub fn handle_event(app: &mut App, event: Event) {
+    if let Event::Key(key) = event
+        && key.kind == KeyEventKind::Press
+    {
+        // The status line is only shown until the next key
+        app.status = None;
     }
 
-    match event::read()? {
-        Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-            KeyCode::Char('q') => return Ok(()),
-            KeyCode::Down => app.next(),
-            KeyCode::Up => app.previous(),
-            KeyCode::Tab => app.toggle_focus(),
-            KeyCode::Esc => app.should_quit = true,
+    match event {
+        Event::Key(key) if key.kind == KeyEventKind::Press && app.export_prompt.is_some() => {
+            match (key.code, app.keymap.action(key)) {
+                // Characters go in the path, even the ones bound to an action
+                (KeyCode::Char(c), _) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.push_export_char(c)
+                }
+                (_, Some(Action::Back)) => app.cancel_export(),
+                (_, Some(Action::Open)) => app.finish_export(),
+                (KeyCode::Backspace, _) => app.pop_export_char(),
+                _ => (),
+            }
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press && app.search_mode => {
+            match (key.code, app.keymap.action(key)) {
+                // Characters go in the filter, even the ones bound to an action
+                (KeyCode::Char(c), _) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.push_search_char(c)
+                }
+                (_, Some(Action::Back)) => app.clear_search(),
+                (_, Some(Action::Open)) => app.finish_search(),
+                (_, Some(Action::Down)) => app.next(),
+                (_, Some(Action::Up)) => app.previous(),
+                (KeyCode::Backspace, _) => app.pop_search_char(),
+                _ => (),
+            }
+        }
+        Event::Key(key) if key.kind == KeyEventKind::Press => {
+            if let Some(action) = app.keymap.action(key) {
+                handle_action(app, action);
+            }
+        }
+        Event::Mouse(mouse) => handle_mouse(app, mouse),
+        _ => (),
+    }
+}
+
+fn handle_action(app: &mut App, action: Action) {
+    if app.show_help {
+        match action {
+            Action::Quit => app.quit(),
+            Action::Help | Action::Back => app.toggle_help(),
+            _ => (),
+        }
+        return;
+    }
+
+    let comparing = app.view == View::Authors && app.comparing;
+    match action {
+        Action::Quit => app.quit(),
+        Action::Help => app.toggle_help(),
+        _ if app.view == View::Authors && app.commit_view.is_some() => {
+            let commit_count = app.selected_user().map_or(0, |u| u.stats.commits.len());
+            match action {
+                Action::Back => app.close_commit_view(),
+                Action::Open => app.open_commit_files(),
+                Action::Down => app.next_commit(),
+                Action::Up => app.previous_commit(),
+                Action::Top => app.select_commit(0),
+                Action::Bottom => app.select_commit(commit_count.saturating_sub(1)),
+                _ => (),
+            }
+        }
+        Action::Back if comparing => app.close_comparison(),
+        Action::Granularity if comparing => app.cycle_granularity(),
+        Action::Metric if comparing => app.toggle_chart_metric(),
+        Action::Export if comparing => app.start_export(ExportTarget::Comparison),
+        Action::NextView => app.next_view(),
+        Action::PreviousView => app.previous_view(),
+        Action::ShowAuthors => app.view = View::Authors,
+        Action::ShowTimeline => app.view = View::Timeline,
+        Action::ShowFiles => app.view = View::Files,
+        Action::ShowSummary => app.view = View::Summary,
+        // The comparison replaces the list, its actions don't apply
+        _ if comparing => (),
+        Action::Back if app.view == View::Authors && !app.filter.is_empty() => app.clear_search(),
+        Action::Back => app.quit(),
+        _ if app.view == View::Files => match action {
+            Action::Down => app.next_file(),
+            Action::Up => app.previous_file(),
+            Action::Top => app.select_file(0),
+            Action::Bottom => app.select_file(app.repo_stats().files.len().saturating_sub(1)),
+            _ => (),
+        },
+        _ if app.view == View::Authors => match action {
+            Action::Open => app.open_commits(),
+            Action::Down => app.next(),
+            Action::Up => app.previous(),
+            Action::Top => app.select_visible_user(0),
+            Action::Bottom => app.select_visible_user(app.visible_users.len().saturating_sub(1)),
+            Action::Sort => app.cycle_sort_key(),
+            Action::ReverseSort => app.reverse_sort(),
+            Action::Search => app.start_search(),
+            Action::Granularity => app.cycle_granularity(),
+            Action::Metric => app.toggle_chart_metric(),
+            Action::Mark => app.toggle_mark(),
+            Action::Compare => app.open_comparison(),
+            Action::Export => app.start_export(ExportTarget::Users),
+            Action::ExportUser => app.start_export(ExportTarget::User),
             _ => (),
         },
         _ => (),
     }
-    Ok(())
+}
+
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    let position = Position::new(mouse.column, mouse.row);
+    let areas = app.areas.clone();
+    let contains = |area: Option<TableArea>| area.is_some_and(|t| t.area.contains(position));
+
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(tab) = areas.tabs.iter().position(|tab| tab.contains(position)) {
+                app.view = View::ALL[tab];
+            } else if let Some(row) = areas
+                .users
+                .filter(|t| t.area.contains(position))
+                .and_then(|t| t.row_at(position.y))
+            {
+                app.select_visible_user(row);
+            } else if let Some(row) = areas
+                .commits
+                .filter(|t| t.area.contains(position))
+                .and_then(|t| t.row_at(position.y))
+            {
+                app.select_commit(row);
+            } else if let Some(row) = areas
+                .files
+                .filter(|t| t.area.contains(position))
+                .and_then(|t| t.row_at(position.y))
+            {
+                app.select_file(row);
+            }
+        }
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let down = mouse.kind == MouseEventKind::ScrollDown;
+            let over_files = areas
+                .commit_files
+                .is_some_and(|area| area.contains(position));
+            if contains(areas.commits) && !over_files {
+                let selected = app.commit_view.as_ref().map_or(0, |v| v.selected);
+                app.select_commit(if down {
+                    selected + 1
+                } else {
+                    selected.saturating_sub(1)
+                });
+            } else if over_files {
+                if down {
+                    app.next_commit();
+                } else {
+                    app.previous_commit();
+                }
+            } else if contains(areas.users) {
+                if down {
+                    app.next();
+                } else {
+                    app.previous();
+                }
+            } else if contains(areas.files) {
+                if down {
+                    app.next_file();
+                } else {
+                    app.previous_file();
+                }
+            }
+        }
+        MouseEventKind::Moved => app.hover(position),
+        _ => (),
+    }
 }
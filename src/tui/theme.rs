@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders};
+use serde::Deserialize;
+
+/// Names of the built-in themes.
+pub const BUILTIN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "none"];
+
+/// Colors used to draw the TUI.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Border of the panes without focus.
+    pub border: Color,
+    /// Border of the pane with focus.
+    pub border_focused: Color,
+    /// Headings, the selected tab and the active sort column.
+    pub title: Color,
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    /// Search matches and commit ids.
+    pub accent: Color,
+    pub bar: Color,
+    pub secondary_bar: Color,
+    /// Text of the values drawn over the bars.
+    pub bar_value: Color,
+    pub added: Color,
+    pub removed: Color,
+    pub error: Color,
+    /// The repository line of the activity charts.
+    pub muted: Color,
+    pub axis: Color,
+    /// Colors of the compared users, in the order they were marked.
+    pub series: Vec<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            border: Color::White,
+            border_focused: Color::Yellow,
+            title: Color::Yellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::LightGreen,
+            accent: Color::Yellow,
+            bar: Color::Green,
+            secondary_bar: Color::Cyan,
+            bar_value: Color::Black,
+            added: Color::Green,
+            removed: Color::Red,
+            error: Color::Red,
+            muted: Color::DarkGray,
+            axis: Color::Gray,
+            series: vec![
+                Color::Green,
+                Color::Cyan,
+                Color::Magenta,
+                Color::Yellow,
+                Color::LightBlue,
+                Color::LightRed,
+            ],
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            border: Color::DarkGray,
+            border_focused: Color::Blue,
+            title: Color::Blue,
+            highlight_fg: Color::White,
+            highlight_bg: Color::Blue,
+            accent: Color::Magenta,
+            bar: Color::Blue,
+            secondary_bar: Color::Magenta,
+            bar_value: Color::White,
+            added: Color::Green,
+            removed: Color::Red,
+            error: Color::Red,
+            muted: Color::DarkGray,
+            axis: Color::DarkGray,
+            series: vec![
+                Color::Blue,
+                Color::Magenta,
+                Color::Green,
+                Color::Red,
+                Color::Cyan,
+                Color::DarkGray,
+            ],
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            border: Color::White,
+            border_focused: Color::LightYellow,
+            title: Color::LightYellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::LightYellow,
+            accent: Color::LightCyan,
+            bar: Color::LightCyan,
+            secondary_bar: Color::LightMagenta,
+            bar_value: Color::Black,
+            added: Color::LightGreen,
+            removed: Color::LightRed,
+            error: Color::LightRed,
+            muted: Color::White,
+            axis: Color::White,
+            series: vec![
+                Color::LightCyan,
+                Color::LightYellow,
+                Color::LightMagenta,
+                Color::LightGreen,
+                Color::LightRed,
+                Color::White,
+            ],
+        }
+    }
+
+    /// Theme without colors, relying on text modifiers only. Used when
+    /// `NO_COLOR` is set.
+    pub fn no_color() -> Self {
+        Theme {
+            border: Color::Reset,
+            border_focused: Color::Reset,
+            title: Color::Reset,
+            highlight_fg: Color::Reset,
+            highlight_bg: Color::Reset,
+            accent: Color::Reset,
+            bar: Color::Reset,
+            secondary_bar: Color::Reset,
+            bar_value: Color::Reset,
+            added: Color::Reset,
+            removed: Color::Reset,
+            error: Color::Reset,
+            muted: Color::Reset,
+            axis: Color::Reset,
+            series: vec![Color::Reset],
+        }
+    }
+
+    /// Built-in theme called `name`, if any.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "none" => Some(Theme::no_color()),
+            _ => None,
+        }
+    }
+
+    /// Theme called `name`, looked up in `custom` first and then in the
+    /// built-in themes.
+    pub fn resolve(name: &str, custom: &HashMap<String, ThemeConfig>) -> Result<Self> {
+        if let Some(config) = custom.get(name) {
+            let base = config.extends.as_deref().unwrap_or("dark");
+            if base == name {
+                bail!("Theme '{}' extends itself", name);
+            }
+            let mut theme = match Theme::builtin(base) {
+                Some(theme) => theme,
+                None => bail!(
+                    "Theme '{}' extends unknown theme '{}', expected one of: {}",
+                    name,
+                    base,
+                    BUILTIN_THEMES.join(", ")
+                ),
+            };
+            config.apply(&mut theme);
+            return Ok(theme);
+        }
+
+        match Theme::builtin(name) {
+            Some(theme) => Ok(theme),
+            None => {
+                let mut names: Vec<&str> = BUILTIN_THEMES.to_vec();
+                names.extend(custom.keys().map(String::as_str));
+                bail!(
+                    "Unknown theme '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                )
+            }
+        }
+    }
+
+    /// Heading text.
+    pub fn heading(&self) -> Style {
+        Style::default().fg(self.title).add_modifier(Modifier::BOLD)
+    }
+
+    pub fn border_style(&self, focused: bool) -> Style {
+        if focused {
+            Style::default().fg(self.border_focused)
+        } else {
+            Style::default().fg(self.border)
+        }
+    }
+
+    /// Bordered pane titled `title`.
+    pub fn block<'a>(&self, title: impl Into<Line<'a>>, focused: bool) -> Block<'a> {
+        Block::default()
+            .title(title)
+            .title_style(Style::default().fg(self.title))
+            .borders(Borders::ALL)
+            .border_style(self.border_style(focused))
+    }
+
+    /// Selected row of a table.
+    pub fn highlight(&self) -> Style {
+        on(self.highlight_fg, self.highlight_bg).add_modifier(Modifier::BOLD)
+    }
+
+    /// Style of the values drawn over bars of `color`.
+    pub fn bar_value(&self, color: Color) -> Style {
+        on(self.bar_value, color)
+    }
+
+    /// Color of the `index`th compared user.
+    pub fn series_color(&self, index: usize) -> Color {
+        match self.series.len() {
+            0 => Color::Reset,
+            len => self.series[index % len],
+        }
+    }
+}
+
+/// `fg` on `bg`, reversed when there is no background color so the text
+/// still stands out.
+fn on(fg: Color, bg: Color) -> Style {
+    if bg == Color::Reset {
+        Style::default().add_modifier(Modifier::REVERSED)
+    } else {
+        Style::default().fg(fg).bg(bg)
+    }
+}
+
+/// A custom theme from the config file. Colors that are not set are taken
+/// from the theme it extends.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Built-in theme this one is based on, `dark` if not set.
+    pub extends: Option<String>,
+    pub border: Option<Color>,
+    pub border_focused: Option<Color>,
+    pub title: Option<Color>,
+    pub highlight_fg: Option<Color>,
+    pub highlight_bg: Option<Color>,
+    pub accent: Option<Color>,
+    pub bar: Option<Color>,
+    pub secondary_bar: Option<Color>,
+    pub bar_value: Option<Color>,
+    pub added: Option<Color>,
+    pub removed: Option<Color>,
+    pub error: Option<Color>,
+    pub muted: Option<Color>,
+    pub axis: Option<Color>,
+    pub series: Option<Vec<Color>>,
+}
+
+impl ThemeConfig {
+    fn apply(&self, theme: &mut Theme) {
+        let colors = [
+            (self.border, &mut theme.border),
+            (self.border_focused, &mut theme.border_focused),
+            (self.title, &mut theme.title),
+            (self.highlight_fg, &mut theme.highlight_fg),
+            (self.highlight_bg, &mut theme.highlight_bg),
+            (self.accent, &mut theme.accent),
+            (self.bar, &mut theme.bar),
+            (self.secondary_bar, &mut theme.secondary_bar),
+            (self.bar_value, &mut theme.bar_value),
+            (self.added, &mut theme.added),
+            (self.removed, &mut theme.removed),
+            (self.error, &mut theme.error),
+            (self.muted, &mut theme.muted),
+            (self.axis, &mut theme.axis),
+        ];
+        for (color, field) in colors {
+            if let Some(color) = color {
+                *field = color;
+            }
+        }
+        if let Some(series) = &self.series {
+            theme.series = series.clone();
+        }
+    }
+}
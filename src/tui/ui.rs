@@ -1,65 +1,650 @@
+use chrono::NaiveDate;
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    symbols::Marker,
     text::{Line, Span},
-    widgets::{BarChart, Block, Borders, List, ListItem, Paragraph, Wrap},
+    widgets::{
+        Axis, BarChart, Cell, Chart, Clear, Dataset, Gauge, GraphType, LegendPosition, Paragraph,
+        Row, Table, TableState, Tabs, Wrap,
+    },
 };
 
-use super::app::App;
+use super::app::{
+    App, BarChartArea, ChartMetric, ExportPrompt, LoadState, ScreenAreas, SortKey, StatusMessage,
+    TableArea, View,
+};
+use super::keymap::Action;
+use super::theme::Theme;
+use crate::analysis::CommitRecord;
+use crate::languages;
+use crate::timeseries::bucket;
+use crate::users::UserInfo;
 
 pub fn render(f: &mut Frame, app: &mut App) {
+    app.areas = ScreenAreas::default();
+    let mut area = f.area();
+    if app.load_state != LoadState::Done {
+        // Reserve the last line for the loading status
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        area = chunks[0];
+        render_load_status(f, app, chunks[1]);
+    }
+
+    if let Some(status) = &app.status {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+        area = chunks[0];
+        render_status(f, &app.theme, status, chunks[1]);
+    }
+
+    if app.users().is_empty() && app.is_loading() {
+        render_loading(f, app, area);
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        render_tabs(f, app, chunks[0]);
+
+        match app.view {
+            View::Authors => render_authors(f, app, chunks[1]),
+            View::Timeline => render_timeline(f, app, chunks[1]),
+            View::Files => render_files(f, app, chunks[1]),
+            View::Summary => render_summary(f, app, chunks[1]),
+        }
+    }
+
+    if let Some(prompt) = &app.export_prompt {
+        render_export_prompt(f, app, prompt, f.area());
+    }
+    if app.show_help {
+        render_help(f, app, f.area());
+    }
+}
+
+/// Popup listing the keys of every action.
+fn render_help(f: &mut Frame, app: &App, area: Rect) {
+    let rows: Vec<Row> = Action::ALL
+        .iter()
+        .map(|action| {
+            Row::new(vec![
+                Cell::from(app.keymap.describe(*action)).style(app.theme.heading()),
+                Cell::from(action.description()),
+            ])
+        })
+        .collect();
+    let key_width = Action::ALL
+        .iter()
+        .map(|action| app.keymap.describe(*action).chars().count())
+        .max()
+        .unwrap_or(0) as u16;
+
+    let popup = centered_rect(70, Action::ALL.len() as u16 + 2, area);
+    let table = Table::new(rows, [Constraint::Length(key_width), Constraint::Min(10)])
+        .column_spacing(2)
+        .block(app.theme.block(
+            format!("Keys ({}: close)", app.keymap.describe(Action::Help)),
+            true,
+        ));
+    f.render_widget(Clear, popup);
+    f.render_widget(table, popup);
+}
+
+fn render_status(f: &mut Frame, theme: &Theme, status: &StatusMessage, area: Rect) {
+    let style = if status.is_error {
+        Style::default().fg(theme.error)
+    } else {
+        Style::default().fg(theme.added)
+    };
+    f.render_widget(Paragraph::new(status.text.as_str()).style(style), area);
+}
+
+/// Popup asking for the path of an export.
+fn render_export_prompt(f: &mut Frame, app: &App, prompt: &ExportPrompt, area: Rect) {
+    let theme = &app.theme;
+    let popup = centered_rect(60, 4, area);
+    let text = vec![
+        Line::from(vec![
+            Span::raw(prompt.path.as_str()),
+            Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+        ]),
+        Line::from(Span::styled(
+            format!(
+                ".md, .json or .csv - {}: write, {}: cancel",
+                app.keymap.describe(Action::Open),
+                app.keymap.describe(Action::Back)
+            ),
+            Style::default().fg(theme.muted),
+        )),
+    ];
+    let paragraph =
+        Paragraph::new(text).block(theme.block(format!("Export {}", prompt.target.label()), true));
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+/// Rectangle of `percent_x` percent of the width of `area` and `height` lines,
+/// centered in it.
+fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
+    // Computed in u32, `area.width * percent_x` overflows u16 on wide terminals
+    let width = (u32::from(area.width) * u32::from(percent_x) / 100) as u16;
+    let width = width.max(20).min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn render_tabs(f: &mut Frame, app: &mut App, area: Rect) {
+    let titles: Vec<String> = View::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, view)| format!("{} {}", i + 1, view.title()))
+        .collect();
+
+    // Each title is padded by a space on both sides, and followed by a divider
+    let mut x = area.x;
+    for title in &titles {
+        let width = title.chars().count() as u16 + 2;
+        app.areas.tabs.push(Rect::new(x, area.y, width, 1));
+        x += width + 1;
+    }
+
+    let tabs = Tabs::new(titles)
+        .select(app.view.index())
+        .highlight_style(app.theme.heading());
+    f.render_widget(tabs, area);
+
+    // Hints are right-aligned, the help one last
+    let mut right = area.right();
+    if let Some(key) = app.keymap.keys(Action::Help).first() {
+        let hint = format!("{}: help", key);
+        let width = hint.chars().count() as u16;
+        if x + width <= right {
+            let hint_area = Rect::new(right - width, area.y, width, 1);
+            f.render_widget(
+                Paragraph::new(hint).style(Style::default().fg(app.theme.muted)),
+                hint_area,
+            );
+            right -= width + 2;
+        }
+    }
+    if let Some(partial) = &app.partial {
+        let hint = format!("shallow clone, since {}", partial.date.format("%Y-%m-%d"));
+        let width = hint.chars().count() as u16;
+        if x + width <= right {
+            let hint_area = Rect::new(right - width, area.y, width, 1);
+            f.render_widget(
+                Paragraph::new(hint).style(Style::default().fg(app.theme.error)),
+                hint_area,
+            );
+        }
+    }
+}
+
+fn render_authors(f: &mut Frame, app: &mut App, area: Rect) {
+    if app.comparing {
+        render_comparison(f, app, area);
+        return;
+    }
+
     // Create main layout with left pane for users and right pane for stats
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-        .split(f.area());
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(area);
 
-    render_users_list(f, app, chunks[0]);
-    render_user_stats(f, app, chunks[1]);
+    if app.search_mode || !app.filter.is_empty() {
+        let users_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(chunks[0]);
+        render_users_list(f, app, users_chunks[0]);
+        render_search(f, app, users_chunks[1]);
+    } else {
+        render_users_list(f, app, chunks[0]);
+    }
+    if app.commit_view.is_some() {
+        render_commits(f, app, chunks[1]);
+    } else {
+        render_user_stats(f, app, chunks[1]);
+    }
 }
 
-fn render_users_list(f: &mut Frame, app: &App, area: Rect) {
-    let items: Vec<ListItem> = app
-        .users
+fn render_timeline(f: &mut Frame, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(area);
+
+    // Show the most recent months that fit, each bar takes 8 columns
+    let visible_months = (chunks[0].width.saturating_sub(2) / 8).max(1) as usize;
+    let months = app.repo_stats().commits_per_month();
+    let month_data: Vec<(String, u64)> = months
         .iter()
-        .map(|user| {
-            ListItem::new(format!("{} <{}>", user.name, user.email)).style(Style::default())
-        })
+        .skip(months.len().saturating_sub(visible_months))
+        .map(|(month, commits)| (month.format("%Y-%m").to_string(), *commits as u64))
         .collect();
+    let month_bars: Vec<(&str, u64)> = month_data.iter().map(|(m, c)| (m.as_str(), *c)).collect();
 
-    let users_list = List::new(items)
+    let month_chart = BarChart::default()
         .block(
-            Block::default()
-                .title("Users")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(if app.focus_users {
-                    Color::Yellow
-                } else {
-                    Color::White
-                })),
+            app.theme
+                .block(bar_chart_title(app, "Commits per Month"), false),
         )
-        .highlight_style(
-            Style::default()
-                .fg(Color::Black)
-                .bg(Color::LightGreen)
-                .add_modifier(Modifier::BOLD),
+        .data(&month_bars)
+        .bar_width(7)
+        .bar_gap(1)
+        .bar_style(Style::default().fg(app.theme.bar))
+        .value_style(app.theme.bar_value(app.theme.bar));
+    f.render_widget(month_chart, chunks[0]);
+    app.areas.bar_charts.push(BarChartArea {
+        title: "Commits per Month",
+        area: chunks[0],
+        bar_width: 7,
+        bar_gap: 1,
+        bars: month_data,
+    });
+
+    let years = app.repo_stats().line_changes_per_year();
+    let year_data: Vec<(String, u64)> = years
+        .iter()
+        .map(|(year, changes)| (year.to_string(), *changes as u64))
+        .collect();
+    let year_bars: Vec<(&str, u64)> = year_data.iter().map(|(y, c)| (y.as_str(), *c)).collect();
+
+    let year_chart = BarChart::default()
+        .block(
+            app.theme
+                .block(bar_chart_title(app, "Lines Changed per Year"), false),
         )
+        .data(&year_bars)
+        .bar_width(9)
+        .bar_gap(2)
+        .bar_style(Style::default().fg(app.theme.secondary_bar))
+        .value_style(app.theme.bar_value(app.theme.secondary_bar));
+    f.render_widget(year_chart, chunks[1]);
+    app.areas.bar_charts.push(BarChartArea {
+        title: "Lines Changed per Year",
+        area: chunks[1],
+        bar_width: 9,
+        bar_gap: 2,
+        bars: year_data,
+    });
+}
+
+/// Title of a bar chart, with the value of the hovered bar if it belongs to it.
+fn bar_chart_title(app: &App, title: &'static str) -> String {
+    match &app.hovered_bar {
+        Some(bar) if bar.chart == title => format!("{} ({}: {})", title, bar.label, bar.value),
+        _ => title.to_string(),
+    }
+}
+
+fn render_files(f: &mut Frame, app: &mut App, area: Rect) {
+    let hotspots = app.repo_stats().hotspots();
+    let rows: Vec<Row> = hotspots
+        .iter()
+        .map(|(path, stats)| {
+            Row::new(vec![
+                Cell::from(path.to_string()),
+                Cell::from(stats.commits.to_string()),
+                Cell::from(stats.authors.len().to_string()),
+                Cell::from(format!("+{}", stats.lines_added))
+                    .style(Style::default().fg(app.theme.added)),
+                Cell::from(format!("-{}", stats.lines_removed))
+                    .style(Style::default().fg(app.theme.removed)),
+            ])
+        })
+        .collect();
+
+    let header = Row::new(vec!["File", "Commits", "Authors", "Added", "Removed"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let files_table = Table::new(
+        rows,
+        [
+            Constraint::Min(10),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ],
+    )
+    .header(header)
+    .block(
+        app.theme
+            .block(format!("Hotspots ({} files)", hotspots.len()), true),
+    )
+    .row_highlight_style(app.theme.highlight())
+    .highlight_symbol(">> ");
+
+    let selected = (!hotspots.is_empty()).then_some(app.selected_file_index);
+    let mut state = TableState::default().with_selected(selected);
+    f.render_stateful_widget(files_table, area, &mut state);
+    app.areas.files = Some(TableArea {
+        area,
+        offset: state.offset(),
+        has_header: true,
+    });
+}
+
+fn render_summary(f: &mut Frame, app: &App, area: Rect) {
+    let stats = app.repo_stats();
+    let format_date = |date: Option<chrono::DateTime<chrono::Utc>>| {
+        date.map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let active_days = stats.commits_per_day.len();
+    let commits_per_active_day = if active_days > 0 {
+        stats.total_commits as f64 / active_days as f64
+    } else {
+        0.0
+    };
+
+    let mut summary_text = vec![
+        Line::from(vec![Span::styled("Repository", app.theme.heading())]),
+        Line::from(""),
+    ];
+    if let Some(partial) = &app.partial {
+        summary_text.push(Line::from(Span::styled(
+            format!(
+                "Shallow clone: commits before {} are missing",
+                partial.date.format("%Y-%m-%d")
+            ),
+            Style::default().fg(app.theme.error),
+        )));
+    }
+    summary_text.extend([
+        Line::from(format!("Total commits: {}", stats.total_commits)),
+        Line::from(format!("Authors: {}", stats.authors.len())),
+        Line::from(format!("Files changed: {}", stats.files.len())),
+        Line::from(format!("First commit: {}", format_date(stats.first_commit))),
+        Line::from(format!("Last commit: {}", format_date(stats.last_commit))),
+        Line::from(format!("Active days: {}", active_days)),
+        Line::from(format!(
+            "Avg commits per active day: {:.2}",
+            commits_per_active_day
+        )),
+        Line::from(""),
+        Line::from(format!("Total lines added: {}", stats.total_lines_added)),
+        Line::from(format!(
+            "Total lines removed: {}",
+            stats.total_lines_removed
+        )),
+        Line::from(Span::styled(
+            format!(
+                "Excluded: +{} -{} in {} generated, vendored or binary files, or ignored commits",
+                stats.excluded_lines_added,
+                stats.excluded_lines_removed,
+                stats.excluded_files.len()
+            ),
+            Style::default().fg(app.theme.muted),
+        )),
+        Line::from(""),
+        Line::from(vec![Span::styled("Top Contributors", app.theme.heading())]),
+        Line::from(""),
+    ]);
+
+    let mut top_users: Vec<_> = app.users().iter().collect();
+    top_users.sort_by_key(|u| std::cmp::Reverse(u.stats.total_commits));
+    for user in top_users.iter().take(5) {
+        let percentage = if stats.total_commits > 0 {
+            user.stats.total_commits as f64 * 100.0 / stats.total_commits as f64
+        } else {
+            0.0
+        };
+        summary_text.push(Line::from(format!(
+            "{} - {} commits ({:.2}%)",
+            user.name, user.stats.total_commits, percentage
+        )));
+    }
+
+    let mut area = area;
+    if app.submodules().next().is_some() {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area);
+        area = chunks[0];
+        render_submodules(f, app, chunks[1]);
+    }
+
+    let summary_widget = Paragraph::new(summary_text)
+        .block(app.theme.block("Summary", false))
+        .wrap(Wrap { trim: true });
+    f.render_widget(summary_widget, area);
+}
+
+/// Breakdown of the commits by submodule, with the top contributors of each.
+fn render_submodules(f: &mut Frame, app: &App, area: Rect) {
+    let mut text = Vec::new();
+    for (name, users) in app.submodules() {
+        let commits: u32 = users.iter().map(|u| u.stats.total_commits).sum();
+        if !text.is_empty() {
+            text.push(Line::from(""));
+        }
+        text.push(Line::from(Span::styled(
+            name.to_string(),
+            app.theme.heading(),
+        )));
+        text.push(Line::from(format!(
+            "{} commits by {} authors",
+            commits,
+            users.len()
+        )));
+
+        let mut top_users: Vec<_> = users.iter().collect();
+        top_users.sort_by_key(|u| std::cmp::Reverse(u.stats.total_commits));
+        for user in top_users.iter().take(3) {
+            text.push(Line::from(format!(
+                "  {} - {} commits",
+                user.name, user.stats.total_commits
+            )));
+        }
+    }
+
+    let widget = Paragraph::new(text)
+        .block(app.theme.block("Submodules", false))
+        .wrap(Wrap { trim: false });
+    f.render_widget(widget, area);
+}
+
+fn render_load_status(f: &mut Frame, app: &App, area: Rect) {
+    match &app.load_state {
+        LoadState::Counting => {
+            f.render_widget(Paragraph::new("Counting commits..."), area);
+        }
+        LoadState::Loading(progress) => {
+            let gauge = Gauge::default()
+                .gauge_style(Style::default().fg(app.theme.bar).bg(app.theme.bar_value))
+                .ratio((progress.percent() / 100.0).clamp(0.0, 1.0))
+                .label(format!(
+                    "Analyzing commits {:.0}% ({}/{})",
+                    progress.percent(),
+                    progress.processed,
+                    progress.total
+                ));
+            f.render_widget(gauge, area);
+        }
+        LoadState::Failed(err) => {
+            let paragraph = Paragraph::new(format!("Analysis failed: {}", err))
+                .style(Style::default().fg(app.theme.error));
+            f.render_widget(paragraph, area);
+        }
+        LoadState::Done => {}
+    }
+}
+
+fn render_loading(f: &mut Frame, app: &App, area: Rect) {
+    let paragraph = Paragraph::new(vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "Loading repository stats...",
+            app.theme.heading(),
+        )),
+        Line::from(""),
+        Line::from("Users will show up as their commits are analyzed."),
+        Line::from(format!(
+            "Press {} to quit, or {} for help.",
+            app.keymap.describe(Action::Quit),
+            app.keymap.describe(Action::Help)
+        )),
+    ])
+    .alignment(ratatui::layout::Alignment::Center)
+    .block(app.theme.block("git-stats", false));
+    f.render_widget(paragraph, area);
+}
+
+fn render_users_list(f: &mut Frame, app: &mut App, area: Rect) {
+    // The last column shows the date the list is sorted by, if any
+    let date_column = match app.sort_key {
+        SortKey::FirstCommit => Some("First"),
+        SortKey::LastCommit => Some("Last"),
+        _ => None,
+    };
+
+    let header_style = |key: SortKey| {
+        if app.sort_key == key {
+            app.theme.heading()
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        }
+    };
+    let mut header = vec![
+        Cell::from("User").style(header_style(SortKey::Name)),
+        Cell::from("Commits").style(header_style(SortKey::Commits)),
+        Cell::from("Added").style(header_style(SortKey::LinesAdded)),
+        Cell::from("Removed").style(header_style(SortKey::LinesRemoved)),
+        Cell::from("Net").style(header_style(SortKey::NetLines)),
+    ];
+    if let Some(title) = date_column {
+        header.push(Cell::from(title).style(header_style(app.sort_key)));
+    }
+
+    let rows: Vec<Row> = app
+        .visible_users
+        .iter()
+        .map(|&i| {
+            let user = &app.users()[i];
+            let mark = if app.is_marked(user) { "* " } else { "" };
+            let user_cell = match app.filter_regex() {
+                // Show the email too, since it may be what matched
+                Some(regex) => {
+                    let mut line = highlight_matches(
+                        &format!("{} <{}>", user.name, user.email),
+                        regex,
+                        &app.theme,
+                    );
+                    line.spans.insert(0, Span::raw(mark));
+                    Cell::from(line)
+                }
+                None => Cell::from(format!("{}{}", mark, user.name)),
+            };
+            let mut cells = vec![
+                user_cell,
+                Cell::from(user.stats.total_commits.to_string()),
+                Cell::from(user.stats.total_lines_added.to_string()),
+                Cell::from(user.stats.total_lines_removed.to_string()),
+                Cell::from(user.stats.net_lines().to_string()),
+            ];
+            if date_column.is_some() {
+                let date = match app.sort_key {
+                    SortKey::FirstCommit => user.stats.first_commit,
+                    _ => user.stats.last_commit,
+                };
+                cells.push(Cell::from(date.map(|d| d.to_string()).unwrap_or_default()));
+            }
+            Row::new(cells)
+        })
+        .collect();
+
+    let mut widths = vec![
+        Constraint::Min(8),
+        Constraint::Length(7),
+        Constraint::Length(7),
+        Constraint::Length(7),
+        Constraint::Length(6),
+    ];
+    if date_column.is_some() {
+        widths.push(Constraint::Length(10));
+    }
+
+    let direction = if app.sort_reversed { "▲" } else { "▼" };
+    let users_table = Table::new(rows, widths)
+        .header(Row::new(header))
+        .block(app.theme.block(users_title(app, direction), true))
+        .row_highlight_style(app.theme.highlight())
         .highlight_symbol(">> ");
 
-    f.render_stateful_widget(
-        users_list,
+    let mut state = TableState::default().with_selected(app.selected_visible_index());
+    f.render_stateful_widget(users_table, area, &mut state);
+    app.areas.users = Some(TableArea {
         area,
-        &mut ratatui::widgets::ListState::default().with_selected(app.selected_user_index),
-    );
+        offset: state.offset(),
+        has_header: true,
+    });
 }
 
-fn render_user_stats(f: &mut Frame, app: &App, area: Rect) {
+fn users_title(app: &App, direction: &str) -> String {
+    let mut title = format!("Users (by {} {})", app.sort_key.label(), direction);
+    if !app.marked_users.is_empty() {
+        title.push_str(&format!(
+            " [{} marked, {}: compare]",
+            app.marked_users.len(),
+            app.keymap.describe(Action::Compare)
+        ));
+    }
+    title
+}
+
+fn render_search(f: &mut Frame, app: &App, area: Rect) {
+    let mut spans = vec![Span::raw("/"), Span::raw(app.filter.as_str())];
+    if app.search_mode {
+        spans.push(Span::styled(
+            " ",
+            Style::default().add_modifier(Modifier::REVERSED),
+        ));
+    }
+
+    let title = format!("Search ({} matches)", app.visible_users.len());
+    let search = Paragraph::new(Line::from(spans)).block(app.theme.block(title, app.search_mode));
+    f.render_widget(search, area);
+}
+
+/// Splits `text` into spans with the matches of `regex` highlighted.
+fn highlight_matches(text: &str, regex: &regex::Regex, theme: &Theme) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for m in regex.find_iter(text).filter(|m| !m.is_empty()) {
+        spans.push(Span::raw(text[last..m.start()].to_string()));
+        spans.push(Span::styled(
+            m.as_str().to_string(),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ));
+        last = m.end();
+    }
+    spans.push(Span::raw(text[last..].to_string()));
+    Line::from(spans)
+}
+
+fn render_user_stats(f: &mut Frame, app: &mut App, area: Rect) {
     let selected_user = match app.selected_user() {
         Some(user) => user,
         None => {
             let paragraph = Paragraph::new("No user selected")
-                .block(Block::default().title("Stats").borders(Borders::ALL))
+                .block(app.theme.block("Stats", false))
                 .wrap(Wrap { trim: true });
             f.render_widget(paragraph, area);
             return;
@@ -79,9 +664,7 @@ fn render_user_stats(f: &mut Frame, app: &App, area: Rect) {
     let stats_text = vec![
         Line::from(vec![Span::styled(
             format!("User: {} <{}>", selected_user.name, selected_user.email),
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
+            app.theme.heading(),
         )]),
         Line::from(""),
         Line::from(format!("Total commits: {}", user_stats.total_commits)),
@@ -114,10 +697,21 @@ fn render_user_stats(f: &mut Frame, app: &App, area: Rect) {
             "Avg lines removed per day: {:.2}",
             user_stats.average_lines_removed_per_day
         )),
+        Line::from(Span::styled(
+            format!(
+                "Excluded lines: +{} -{}",
+                user_stats.excluded_lines_added, user_stats.excluded_lines_removed
+            ),
+            Style::default().fg(app.theme.muted),
+        )),
+        Line::from(format!(
+            "Files renamed: {}, copied: {}",
+            user_stats.renames, user_stats.copies
+        )),
     ];
 
     let stats_widget = Paragraph::new(stats_text)
-        .block(Block::default().title("Statistics").borders(Borders::ALL))
+        .block(app.theme.block("Statistics", false))
         .wrap(Wrap { trim: true });
 
     f.render_widget(stats_widget, chunks[0]);
@@ -125,39 +719,38 @@ fn render_user_stats(f: &mut Frame, app: &App, area: Rect) {
     // Create charts section with multiple visualizations
     let charts_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
         .split(chunks[1]);
+    let side_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(charts_chunks[1]);
 
-    // Hold the year data as strings for later use
-    let year_strings: Vec<String> = user_stats
-        .line_changes_per_year
-        .keys()
-        .map(|year| year.to_string())
-        .collect();
-    // Render a bar chart for line changes by year
-    let year_data: Vec<(&str, u64)> = user_stats
+    render_user_activity_chart(f, app, selected_user, charts_chunks[0]);
+
+    // Render a bar chart for line changes by year, in chronological order
+    let year_data: Vec<(String, u64)> = user_stats
         .line_changes_per_year
         .iter()
-        .enumerate()
-        .map(|(i, (_, changes))| (year_strings[i].as_str(), *changes as u64))
+        .map(|(year, changes)| (year.to_string(), *changes as u64))
         .collect();
+    let year_bars: Vec<(&str, u64)> = year_data.iter().map(|(y, c)| (y.as_str(), *c)).collect();
 
     let bar_chart = BarChart::default()
         .block(
-            Block::default()
-                .title("Changes by Year")
-                .borders(Borders::ALL),
+            app.theme
+                .block(bar_chart_title(app, "Changes by Year"), false),
         )
-        .data(&year_data)
+        .data(&year_bars)
         .bar_width(9)
         .bar_gap(2)
-        .bar_style(Style::default().fg(Color::Green))
-        .value_style(Style::default().fg(Color::Black).bg(Color::Green));
+        .bar_style(Style::default().fg(app.theme.bar))
+        .value_style(app.theme.bar_value(app.theme.bar));
 
-    f.render_widget(bar_chart, charts_chunks[0]);
+    f.render_widget(bar_chart, side_chunks[0]);
 
     // Render a contribution percentage section
-    let total_project_commits: u32 = app.users.iter().map(|u| u.stats.total_commits).sum();
+    let total_project_commits: u32 = app.users().iter().map(|u| u.stats.total_commits).sum();
     let contribution_percentage = if total_project_commits > 0 {
         (selected_user.stats.total_commits as f64 / total_project_commits as f64) * 100.0
     } else {
@@ -167,9 +760,7 @@ fn render_user_stats(f: &mut Frame, app: &App, area: Rect) {
     let contribution_text = vec![
         Line::from(vec![Span::styled(
             "Contribution Percentage",
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
+            app.theme.heading(),
         )]),
         Line::from(""),
         Line::from(format!("{:.2}% of total commits", contribution_percentage)),
@@ -178,14 +769,418 @@ fn render_user_stats(f: &mut Frame, app: &App, area: Rect) {
     ];
 
     let contribution_widget = Paragraph::new(contribution_text)
-        .block(
-            Block::default()
-                .title("Project Contribution")
-                .borders(Borders::ALL),
-        )
+        .block(app.theme.block("Project Contribution", false))
         .wrap(Wrap { trim: true });
 
-    f.render_widget(contribution_widget, charts_chunks[1]);
+    let bottom_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(side_chunks[1]);
+    f.render_widget(contribution_widget, bottom_chunks[0]);
+    render_user_languages(f, app, selected_user, bottom_chunks[1]);
+    app.areas.bar_charts.push(BarChartArea {
+        title: "Changes by Year",
+        area: side_chunks[0],
+        bar_width: 9,
+        bar_gap: 2,
+        bars: year_data,
+    });
+}
+
+/// Share of the line changes of `user` in each language, biggest first.
+fn render_user_languages(f: &mut Frame, app: &App, user: &UserInfo, area: Rect) {
+    let lines: Vec<Line> = languages::distribution(&user.stats.languages)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (language, share))| {
+            Line::from(vec![
+                Span::styled("■ ", Style::default().fg(app.theme.series_color(i))),
+                Span::raw(format!("{} {:.0}%", language, share)),
+            ])
+        })
+        .collect();
+
+    let widget = Paragraph::new(lines)
+        .block(app.theme.block("Languages", false))
+        .wrap(Wrap { trim: true });
+    f.render_widget(widget, area);
+}
+
+/// Formats one of the stats of a user.
+type UserMetric = fn(&UserInfo) -> String;
+
+fn render_comparison(f: &mut Frame, app: &App, area: Rect) {
+    let users = app.compared_users();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(14), Constraint::Min(0)])
+        .split(area);
+
+    let total_commits = app.repo_stats().total_commits;
+    let metrics: [(&str, UserMetric); 10] = [
+        ("Email", |u| u.email.clone()),
+        ("Total commits", |u| u.stats.total_commits.to_string()),
+        ("Avg commits per day", |u| {
+            format!("{:.2}", u.stats.average_commits_per_day)
+        }),
+        ("Avg commits per month", |u| {
+            format!("{:.2}", u.stats.average_commits_per_month)
+        }),
+        ("Lines added", |u| u.stats.total_lines_added.to_string()),
+        ("Lines removed", |u| u.stats.total_lines_removed.to_string()),
+        ("Net lines", |u| u.stats.net_lines().to_string()),
+        ("Avg lines added per day", |u| {
+            format!("{:.2}", u.stats.average_lines_added_per_day)
+        }),
+        ("First commit", |u| {
+            format_optional_date(u.stats.first_commit)
+        }),
+        ("Last commit", |u| format_optional_date(u.stats.last_commit)),
+    ];
+
+    let mut rows: Vec<Row> = metrics
+        .iter()
+        .map(|(label, value)| {
+            let mut cells =
+                vec![Cell::from(*label).style(Style::default().add_modifier(Modifier::BOLD))];
+            cells.extend(users.iter().map(|u| Cell::from(value(u))));
+            Row::new(cells)
+        })
+        .collect();
+    let mut share =
+        vec![Cell::from("Share of commits").style(Style::default().add_modifier(Modifier::BOLD))];
+    share.extend(users.iter().map(|u| {
+        let percentage = if total_commits > 0 {
+            u.stats.total_commits as f64 * 100.0 / total_commits as f64
+        } else {
+            0.0
+        };
+        Cell::from(format!("{:.2}%", percentage))
+    }));
+    rows.push(Row::new(share));
+
+    let header: Vec<Cell> = std::iter::once(Cell::from(""))
+        .chain(users.iter().enumerate().map(|(i, u)| {
+            Cell::from(u.name.clone()).style(
+                Style::default()
+                    .fg(app.theme.series_color(i))
+                    .add_modifier(Modifier::BOLD),
+            )
+        }))
+        .collect();
+    let widths: Vec<Constraint> = std::iter::once(Constraint::Length(24))
+        .chain(users.iter().map(|_| Constraint::Fill(1)))
+        .collect();
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header))
+        .block(app.theme.block(
+            format!("Comparison ({}: back)", app.keymap.describe(Action::Back)),
+            true,
+        ));
+    f.render_widget(table, chunks[0]);
+
+    let series = users
+        .iter()
+        .enumerate()
+        .map(|(i, u)| ActivitySeries {
+            name: u.name.as_str(),
+            color: app.theme.series_color(i),
+            values: user_activity(u, app.chart_metric),
+        })
+        .collect();
+    render_activity_chart(f, app, series, chunks[1]);
+}
+
+fn format_optional_date(date: Option<NaiveDate>) -> String {
+    date.map(|d| d.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// A line of an activity chart.
+struct ActivitySeries<'a> {
+    name: &'a str,
+    color: Color,
+    /// Value of the chart metric for each day with activity.
+    values: Vec<(NaiveDate, usize)>,
+}
+
+fn user_activity(user: &UserInfo, metric: ChartMetric) -> Vec<(NaiveDate, usize)> {
+    user.stats
+        .commits
+        .iter()
+        .map(|c| {
+            let value = match metric {
+                ChartMetric::Commits => 1,
+                ChartMetric::Lines => c.lines_added + c.lines_removed,
+            };
+            (c.time.date_naive(), value)
+        })
+        .collect()
+}
+
+fn repo_activity(app: &App) -> Vec<(NaiveDate, usize)> {
+    let per_day = match app.chart_metric {
+        ChartMetric::Commits => &app.repo_stats().commits_per_day,
+        ChartMetric::Lines => &app.repo_stats().line_changes_per_day,
+    };
+    per_day.iter().map(|(d, v)| (*d, *v)).collect()
+}
+
+/// Line chart of the activity of `user` over time, with the activity of the
+/// whole repository as a reference.
+fn render_user_activity_chart(f: &mut Frame, app: &App, user: &UserInfo, area: Rect) {
+    let series = vec![
+        ActivitySeries {
+            name: "Repository",
+            color: app.theme.muted,
+            values: repo_activity(app),
+        },
+        ActivitySeries {
+            name: user.name.as_str(),
+            color: app.theme.bar,
+            values: user_activity(user, app.chart_metric),
+        },
+    ];
+    render_activity_chart(f, app, series, area);
+}
+
+/// Line chart of each series over the history of the repository, grouped by
+/// the selected granularity.
+fn render_activity_chart(f: &mut Frame, app: &App, series: Vec<ActivitySeries>, area: Rect) {
+    let granularity = app.granularity;
+    let metric = match app.chart_metric {
+        ChartMetric::Commits => "Commits",
+        ChartMetric::Lines => "Lines changed",
+    };
+    let title = format!(
+        "{} per {} ({}: period, {}: metric)",
+        metric,
+        granularity.label(),
+        app.keymap.describe(Action::Granularity),
+        app.keymap.describe(Action::Metric)
+    );
+    let block = app.theme.block(title, false);
+
+    let (Some(from), Some(to)) = (
+        app.repo_stats().commits_per_day.keys().next().copied(),
+        app.repo_stats().commits_per_day.keys().next_back().copied(),
+    ) else {
+        f.render_widget(Paragraph::new("No activity").block(block), area);
+        return;
+    };
+
+    let points: Vec<Vec<(f64, f64)>> = series
+        .iter()
+        .map(|s| {
+            bucket(s.values.iter().copied(), granularity, from, to)
+                .into_iter()
+                .enumerate()
+                .map(|(i, (_, value))| (i as f64, value as f64))
+                .collect()
+        })
+        .collect();
+    let periods = bucket(std::iter::empty(), granularity, from, to);
+
+    let max_x = (periods.len().saturating_sub(1)).max(1) as f64;
+    let max_y = points.iter().flatten().map(|(_, y)| *y).fold(1.0, f64::max);
+    let x_labels: Vec<Span> = [
+        periods.first(),
+        periods.get(periods.len() / 2),
+        periods.last(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(start, _)| Span::raw(granularity.format(*start)))
+    .collect();
+
+    let datasets = series
+        .iter()
+        .zip(&points)
+        .map(|(s, points)| {
+            Dataset::default()
+                .name(s.name)
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(s.color))
+                .data(points)
+        })
+        .collect();
+
+    let chart = Chart::new(datasets)
+        .block(block)
+        .x_axis(
+            Axis::default()
+                .bounds([0.0, max_x])
+                .labels(x_labels)
+                .style(Style::default().fg(app.theme.axis)),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max_y])
+                .labels(vec![
+                    Span::raw("0"),
+                    Span::raw(format!("{:.0}", max_y / 2.0)),
+                    Span::raw(format!("{:.0}", max_y)),
+                ])
+                .style(Style::default().fg(app.theme.axis)),
+        )
+        .legend_position(Some(LegendPosition::TopLeft))
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)));
+
+    f.render_widget(chart, area);
+}
+
+fn render_commits(f: &mut Frame, app: &mut App, area: Rect) {
+    let (Some(user), Some(view)) = (app.selected_user(), app.commit_view.as_ref()) else {
+        return;
+    };
+
+    let chunks = if view.show_files {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area)
+    } else {
+        Layout::default()
+            .constraints([Constraint::Percentage(100)])
+            .split(area)
+    };
+
+    let rows: Vec<Row> = user
+        .stats
+        .commits
+        .iter()
+        .map(|commit| {
+            Row::new(vec![
+                Cell::from(commit.oid.to_string()[..8].to_string())
+                    .style(Style::default().fg(app.theme.accent)),
+                Cell::from(commit.time.format("%Y-%m-%d").to_string()),
+                Cell::from(commit.summary.clone()),
+                Cell::from(format!("+{}", commit.lines_added))
+                    .style(Style::default().fg(app.theme.added)),
+                Cell::from(format!("-{}", commit.lines_removed))
+                    .style(Style::default().fg(app.theme.removed)),
+            ])
+        })
+        .collect();
+
+    let commits_table = Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(10),
+            Constraint::Min(10),
+            Constraint::Length(7),
+            Constraint::Length(7),
+        ],
+    )
+    .block(app.theme.block(
+        format!("Commits of {} ({})", user.name, user.stats.commits.len()),
+        !view.show_files,
+    ))
+    .row_highlight_style(app.theme.highlight())
+    .highlight_symbol(">> ");
+
+    let mut state = TableState::default().with_selected(Some(view.selected));
+    f.render_stateful_widget(commits_table, chunks[0], &mut state);
+
+    let show_files = view.show_files;
+    if show_files && let Some(commit) = app.selected_commit() {
+        render_commit_files(f, &app.theme, commit, view.file_scroll, chunks[1]);
+    }
+
+    app.areas.commits = Some(TableArea {
+        area: chunks[0],
+        offset: state.offset(),
+        has_header: false,
+    });
+    if show_files {
+        app.areas.commit_files = Some(chunks[1]);
+    }
+}
+
+fn render_commit_files(
+    f: &mut Frame,
+    theme: &Theme,
+    commit: &CommitRecord,
+    scroll: usize,
+    area: Rect,
+) {
+    let max_changes = commit
+        .files
+        .iter()
+        .map(|file| file.lines_added + file.lines_removed)
+        .max()
+        .unwrap_or(0);
+
+    let counted = commit.files.iter().map(|file| {
+        let (added, removed) = diffstat_bar(file.lines_added, file.lines_removed, max_changes);
+        Row::new(vec![
+            Cell::from(file.path.clone()),
+            Cell::from(format!("+{}", file.lines_added)).style(Style::default().fg(theme.added)),
+            Cell::from(format!("-{}", file.lines_removed))
+                .style(Style::default().fg(theme.removed)),
+            Cell::from(Line::from(vec![
+                Span::styled(added, Style::default().fg(theme.added)),
+                Span::styled(removed, Style::default().fg(theme.removed)),
+            ])),
+        ])
+    });
+    // Left out of the line counts, so listed after the others without a bar
+    let excluded = commit.excluded_files.iter().map(|file| {
+        Row::new(vec![
+            Cell::from(format!("{} (excluded)", file.path)),
+            Cell::from(format!("+{}", file.lines_added)),
+            Cell::from(format!("-{}", file.lines_removed)),
+        ])
+        .style(Style::default().fg(theme.muted))
+    });
+    let rows: Vec<Row> = counted.chain(excluded).skip(scroll).collect();
+
+    let files_table = Table::new(
+        rows,
+        [
+            Constraint::Min(10),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(DIFFSTAT_WIDTH as u16),
+        ],
+    )
+    .block(theme.block(
+        format!(
+            "{} {} ({} files, +{} -{}{})",
+            &commit.oid.to_string()[..8],
+            commit.summary,
+            commit.files.len(),
+            commit.lines_added,
+            commit.lines_removed,
+            match commit.excluded_files.len() {
+                0 => String::new(),
+                excluded => format!(", {} excluded", excluded),
+            }
+        ),
+        true,
+    ));
+
+    f.render_widget(files_table, area);
+}
+
+const DIFFSTAT_WIDTH: usize = 20;
+
+/// `git diff --stat` like bar, scaled so the biggest change fills the width.
+fn diffstat_bar(added: usize, removed: usize, max_changes: usize) -> (String, String) {
+    let total = added + removed;
+    if total == 0 {
+        return (String::new(), String::new());
+    }
+    let width = if max_changes <= DIFFSTAT_WIDTH {
+        total
+    } else {
+        (total * DIFFSTAT_WIDTH / max_changes).max(1)
+    };
+    let plus = (added * width).div_ceil(total).min(width);
+    ("+".repeat(plus), "-".repeat(width - plus))
 }
 
 fn render_percentage_bar(percentage: usize) -> String {
@@ -1,10 +1,17 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use anyhow::{Context, Result};
 use chrono::prelude::*;
-use git2::{Commit, Repository};
+use git2::{Commit, Delta, Oid, Patch, Repository, Revwalk};
 use uuid::Uuid;
 
+use crate::analysis::{CommitRecord, FileChange};
+use crate::churn::Churn;
+use crate::error::{Result, StatsError};
+use crate::languages::{LanguageStats, language_of};
+use crate::query::StatsQuery;
+use crate::repo_stats::RepoStats;
+use crate::repository;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct UserInfo {
     pub id: Uuid,
@@ -27,9 +34,32 @@ pub struct UserStats {
     pub average_lines_removed_per_month: f32,
     pub average_lines_added_per_year: f32,
     pub average_lines_removed_per_year: f32,
-    pub line_changes_per_year: HashMap<u32, usize>,
-    pub line_changes_per_month: HashMap<u32, usize>,
-    pub line_changes_per_day: HashMap<u32, usize>,
+    /// Lines changed in the files left out of the counts above, see
+    /// [`ChurnOptions`](crate::churn::ChurnOptions).
+    pub excluded_lines_added: usize,
+    pub excluded_lines_removed: usize,
+    /// Files renamed or copied by the user, see
+    /// [`ChurnOptions::similarity_threshold`](crate::churn::ChurnOptions::similarity_threshold).
+    pub renames: usize,
+    pub copies: usize,
+    pub line_changes_per_year: BTreeMap<u32, usize>,
+    /// Lines added plus removed per month, keyed by the first day of the month.
+    pub line_changes_per_month: BTreeMap<NaiveDate, usize>,
+    /// Lines added plus removed on each day with activity.
+    pub line_changes_per_day: BTreeMap<NaiveDate, usize>,
+    pub first_commit: Option<NaiveDate>,
+    pub last_commit: Option<NaiveDate>,
+    /// Lines changed in the files of each language, see [`language_of`].
+    pub languages: BTreeMap<&'static str, LanguageStats>,
+    /// Every commit of the user, newest first.
+    pub commits: Vec<CommitRecord>,
+}
+
+impl UserStats {
+    /// Lines added minus lines removed.
+    pub fn net_lines(&self) -> i64 {
+        self.total_lines_added as i64 - self.total_lines_removed as i64
+    }
 }
 
 impl Eq for UserInfo {}
@@ -58,99 +88,195 @@ impl UserInfo {
     }
 }
 
-pub fn get_users(repo: &Repository) -> Result<Vec<UserInfo>> {
-    let mut users = HashSet::new();
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-
-    for oid in revwalk {
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
-        let author = commit.author();
-        let user_info = UserInfo::new(
-            author.name().unwrap_or("Unknown").to_string(),
-            author.email().unwrap_or("Unknown").to_string(),
-        );
-        users.insert(user_info);
+/// A repository whose history should be included in the stats, along with the
+/// commit to start walking from.
+#[derive(Clone, Copy)]
+pub struct HistorySource<'a> {
+    pub repo: &'a Repository,
+    /// Commit to walk from, `None` walks from `HEAD`.
+    pub start: Option<Oid>,
+    /// Name the commits of this history are tagged with, see
+    /// [`CommitRecord::source`].
+    pub name: Option<&'a str>,
+}
+
+impl<'a> HistorySource<'a> {
+    pub fn head(repo: &'a Repository) -> Self {
+        HistorySource {
+            repo,
+            start: None,
+            name: None,
+        }
     }
 
-    Ok(users.into_iter().collect())
+    pub(crate) fn revwalk(&self) -> Result<Revwalk<'a>> {
+        let mut revwalk = self.repo.revwalk()?;
+        match self.start {
+            Some(oid) => revwalk.push(oid)?,
+            None => revwalk
+                .push_head()
+                .map_err(|err| repository::head_error(self.repo, err))?,
+        }
+        Ok(revwalk)
+    }
 }
 
-pub fn update_user_stats(
-    users: &mut [UserInfo],
-    repo: &Repository,
-    _branch: &str,
-) -> anyhow::Result<()> {
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push_head()?;
-
-    #[derive(Default)]
-    struct InternalUserStats {
-        commit_by_date: HashMap<NaiveDate, u64>,
-        changes_by_date: HashMap<NaiveDate, (usize, usize)>,
+/// Every author of the history of `HEAD`, without their stats, see
+/// [`update_user_stats`]. [`StatsQuery`] finds the authors and computes their
+/// stats in a single walk.
+pub fn get_users(repo: &Repository) -> Result<Vec<UserInfo>> {
+    get_users_from(&[HistorySource::head(repo)])
+}
+
+/// Collects the authors of every commit reachable from the given sources.
+/// Only the commits are read, none of them is diffed.
+pub fn get_users_from(sources: &[HistorySource]) -> Result<Vec<UserInfo>> {
+    let Some(query) = query_of(sources) else {
+        return Ok(Vec::new());
+    };
+    let mut accumulator = StatsAccumulator::new();
+    for (source, revwalk) in sources.iter().zip(query.revwalks()?) {
+        for oid in revwalk {
+            let commit = source.repo.find_commit(oid?)?;
+            let author = commit.author();
+            accumulator.add_author(
+                author.name().unwrap_or("Unknown"),
+                author.email().unwrap_or("Unknown"),
+            );
+        }
     }
+    Ok(accumulator.current_users().to_vec())
+}
 
-    let mut user_stats: HashMap<Uuid, InternalUserStats> = HashMap::new();
+pub fn update_user_stats(users: &mut [UserInfo], repo: &Repository, _branch: &str) -> Result<()> {
+    update_user_stats_from(users, &[HistorySource::head(repo)])
+}
+
+/// Updates the stats of `users` with the history of every source, so commits
+/// from several repositories (e.g. submodules) are folded into the same totals.
+/// Commits of other authors are skipped.
+pub fn update_user_stats_from(users: &mut [UserInfo], sources: &[HistorySource]) -> Result<()> {
+    let Some(query) = query_of(sources) else {
+        return Ok(());
+    };
 
     let progress = indicatif::ProgressBar::new_spinner();
     progress.set_message("Processing commits...");
     progress.set_style(
-        indicatif::ProgressStyle::with_template("{spinner} {msg} commits {per_sec} [{elapsed_precise}]")
-            .unwrap()
-            .tick_chars("⠋⠙⠸⠼⠧⠇⠏"),
+        indicatif::ProgressStyle::with_template(
+            "{spinner} {msg} commits {per_sec} [{elapsed_precise}]",
+        )
+        .unwrap()
+        .tick_chars("⠋⠙⠸⠼⠧⠇⠏"),
     );
 
-    for oid in revwalk {
+    // The stats are computed again from scratch, for the same users
+    let known = users
+        .iter()
+        .map(|user| UserInfo {
+            stats: UserStats::default(),
+            ..user.clone()
+        })
+        .collect();
+    let mut accumulator = StatsAccumulator::from_users(known, RepoStats::default());
+    for record in query.walk()? {
         progress.tick();
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
-        let author = commit.author();
-
-        let user_email = author.email().unwrap_or_default();
-        let user_name = author.name().unwrap_or_default();
-        let Some(user) = users.iter().find(|u| u.email == user_email || u.name == user_name) else {
-            continue;
-        };
-
-        let commit_date = utc_from_commit(&commit)?.date_naive();
-        // let (added, removed) = get_lines_changed(repo, &commit)?;
-        let added = 0;
-        let removed = 0;
-
-        user_stats
-            .entry(user.id)
-            .and_modify(|u| {
-                u.commit_by_date
-                    .entry(commit_date)
-                    .and_modify(|x| *x += 1)
-                    .or_insert(1);
-                u.changes_by_date
-                    .entry(commit_date)
-                    .and_modify(|(a, b)| {
-                        *a += added;
-                        *b += removed;
-                    })
-                    .or_insert((added, removed));
-            })
-            .or_default();
+        if let Some(record) = record? {
+            accumulator.add(record);
+        }
     }
     progress.finish_and_clear();
 
+    let updated: HashMap<Uuid, &UserStats> = accumulator
+        .users()
+        .iter()
+        .filter(|user| !user.stats.commits.is_empty())
+        .map(|user| (user.id, &user.stats))
+        .collect();
     for user in users.iter_mut() {
-        let Some(stats) = user_stats.get(&user.id) else {
-            continue;
-        };
+        if let Some(stats) = updated.get(&user.id) {
+            user.stats = (*stats).clone();
+        }
+    }
 
-        user.stats.total_commits = stats.commit_by_date.values().sum::<u64>() as u32;
+    Ok(())
+}
+
+/// Query over `sources`, the first one being the main history.
+fn query_of<'r>(sources: &[HistorySource<'r>]) -> Option<StatsQuery<'r>> {
+    let (main, includes) = sources.split_first()?;
+    let mut query = StatsQuery::new(main.repo);
+    if let Some(start) = main.start {
+        query = query.revspec(start.to_string());
+    }
+    Some(
+        includes
+            .iter()
+            .fold(query, |query, source| query.include(*source)),
+    )
+}
+
+/// Raw per-user data the [`UserStats`] are derived from.
+#[derive(Default)]
+struct InternalUserStats {
+    commit_by_date: BTreeMap<NaiveDate, u64>,
+    changes_by_date: BTreeMap<NaiveDate, (usize, usize)>,
+    languages: BTreeMap<&'static str, LanguageStats>,
+    excluded_lines_added: usize,
+    excluded_lines_removed: usize,
+    renames: usize,
+    copies: usize,
+    commits: Vec<CommitRecord>,
+}
 
-        let total_days = stats.commit_by_date.len() as f32;
+impl InternalUserStats {
+    fn add(&mut self, record: CommitRecord) {
+        let date = record.time.date_naive();
+        *self.commit_by_date.entry(date).or_default() += 1;
+        let (added, removed) = self.changes_by_date.entry(date).or_default();
+        *added += record.lines_added;
+        *removed += record.lines_removed;
+        for file in &record.files {
+            if file.lines_added + file.lines_removed == 0 {
+                continue;
+            }
+            let language = self.languages.entry(language_of(&file.path)).or_default();
+            language.lines_added += file.lines_added;
+            language.lines_removed += file.lines_removed;
+        }
+        for file in &record.excluded_files {
+            self.excluded_lines_added += file.lines_added;
+            self.excluded_lines_removed += file.lines_removed;
+        }
+        self.renames += record.renames;
+        self.copies += record.copies;
+        self.commits.push(record);
+    }
+
+    /// Replaces the stats of `user` with the ones derived from this data.
+    fn apply(&self, user: &mut UserInfo) {
+        user.stats = UserStats::default();
+
+        user.stats.total_commits = self.commit_by_date.values().sum::<u64>() as u32;
+        user.stats.first_commit = self.commit_by_date.keys().min().copied();
+        user.stats.last_commit = self.commit_by_date.keys().max().copied();
+        user.stats.languages = self.languages.clone();
+        user.stats.excluded_lines_added = self.excluded_lines_added;
+        user.stats.excluded_lines_removed = self.excluded_lines_removed;
+        user.stats.renames = self.renames;
+        user.stats.copies = self.copies;
+        user.stats.commits = self.commits.clone();
+        user.stats
+            .commits
+            .sort_by_key(|c| std::cmp::Reverse(c.time));
+
+        let total_days = self.commit_by_date.len() as f32;
         user.stats.average_commits_per_day = if total_days > 0.0 {
             user.stats.total_commits as f32 / total_days
         } else {
             0.0
         };
-        let total_years = stats
+        let total_years = self
             .commit_by_date
             .keys()
             .map(|d| d.year())
@@ -162,10 +288,11 @@ pub fn update_user_stats(
             0.0
         };
 
-        let total_months = stats
+        // January of two years are two months
+        let total_months = self
             .commit_by_date
             .keys()
-            .map(|d| d.month())
+            .map(|d| (d.year(), d.month()))
             .collect::<HashSet<_>>()
             .len() as f32;
         user.stats.average_commits_per_month = if total_months > 0.0 {
@@ -174,34 +301,23 @@ pub fn update_user_stats(
             0.0
         };
 
-        for (date, (added, removed)) in &stats.changes_by_date {
-            let year = date.year();
-            let month = date.month();
-            let day = date.day();
-
-            user.stats
+        for (date, (added, removed)) in &self.changes_by_date {
+            let changes = added + removed;
+            *user
+                .stats
                 .line_changes_per_year
-                .entry(year as u32)
-                .and_modify(|v| *v += added + removed)
-                .or_insert(0);
-            user.stats
+                .entry(date.year() as u32)
+                .or_default() += changes;
+            *user
+                .stats
                 .line_changes_per_month
-                .entry(month)
-                .and_modify(|v| *v += added + removed)
-                .or_insert(0);
-            user.stats
-                .line_changes_per_day
-                .entry(day)
-                .and_modify(|v| *v += added + removed)
-                .or_insert(0);
+                .entry(date.with_day(1).unwrap_or(*date))
+                .or_default() += changes;
+            *user.stats.line_changes_per_day.entry(*date).or_default() += changes;
         }
 
-        user.stats.total_lines_added = stats
-            .changes_by_date
-            .values()
-            .map(|(added, _)| *added)
-            .sum();
-        user.stats.total_lines_removed = stats
+        user.stats.total_lines_added = self.changes_by_date.values().map(|(added, _)| *added).sum();
+        user.stats.total_lines_removed = self
             .changes_by_date
             .values()
             .map(|(_, removed)| *removed)
@@ -239,42 +355,262 @@ pub fn update_user_stats(
             0.0
         };
     }
+}
 
-    Ok(())
+/// Builds users and their stats incrementally from commit records, e.g. while
+/// an [`Analysis`](crate::analysis::Analysis) is still running.
+#[derive(Default)]
+pub struct StatsAccumulator {
+    users: Vec<UserInfo>,
+    /// Ids of the users by email and by name, to find the author of a commit.
+    ids_by_email: HashMap<String, Uuid>,
+    ids_by_name: HashMap<String, Uuid>,
+    repo_stats: RepoStats,
+    stats: HashMap<Uuid, InternalUserStats>,
+    dirty: HashSet<Uuid>,
+    /// Stats of the commits of each source, by [`CommitRecord::source`].
+    sources: BTreeMap<String, StatsAccumulator>,
 }
 
-fn utc_from_commit(commit: &Commit) -> anyhow::Result<DateTime<Utc>> {
-    let base_time =
-        DateTime::from_timestamp(commit.time().seconds(), 0).context("failed to convert")?;
+impl StatsAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Continues from `users` and `repo_stats`, e.g. the ones of a
+    /// [`Report`](crate::query::Report). The stats of a user are kept as they
+    /// are until a commit of theirs is added.
+    pub fn from_users(users: Vec<UserInfo>, repo_stats: RepoStats) -> Self {
+        let mut accumulator = StatsAccumulator {
+            repo_stats,
+            ..Self::default()
+        };
+        for user in users {
+            accumulator.ids_by_email.insert(user.email.clone(), user.id);
+            accumulator.ids_by_name.insert(user.name.clone(), user.id);
+            accumulator.users.push(user);
+        }
+        accumulator
+    }
+
+    /// Continues the stats of the source `name` from `users`, like
+    /// [`StatsAccumulator::from_users`].
+    pub fn with_source(mut self, name: impl Into<String>, users: Vec<UserInfo>) -> Self {
+        let repo_stats = RepoStats::from_users(&users);
+        self.sources
+            .insert(name.into(), StatsAccumulator::from_users(users, repo_stats));
+        self
+    }
+
+    /// Adds a commit to the stats of its author, who is created if it's the
+    /// first commit seen from them. Returns the id of the author.
+    ///
+    /// A commit with a [`source`](CommitRecord::source) is also added to the
+    /// stats of that source.
+    pub fn add(&mut self, record: CommitRecord) -> Uuid {
+        if let Some(source) = &record.source {
+            self.sources
+                .entry(source.clone())
+                .or_default()
+                .add_commit(record.clone());
+        }
+        self.add_commit(record)
+    }
+
+    fn add_commit(&mut self, record: CommitRecord) -> Uuid {
+        let id = self.add_author(&record.author_name, &record.author_email);
+        self.repo_stats.add(&record);
+        let users = &self.users;
+        self.stats
+            .entry(id)
+            .or_insert_with(|| {
+                // A user given to `from_users` starts from their commits
+                let mut stats = InternalUserStats::default();
+                let commits = users.iter().find(|u| u.id == id);
+                for commit in commits.into_iter().flat_map(|u| &u.stats.commits) {
+                    stats.add(commit.clone());
+                }
+                stats
+            })
+            .add(record);
+        self.dirty.insert(id);
+        id
+    }
+
+    /// Id of the author known by `email` or `name`, who is created without
+    /// stats if it's the first time they're seen.
+    pub(crate) fn add_author(&mut self, name: &str, email: &str) -> Uuid {
+        let known = self
+            .ids_by_email
+            .get(email)
+            .or_else(|| self.ids_by_name.get(name));
+        if let Some(id) = known {
+            return *id;
+        }
+        let user = UserInfo::new(name.to_string(), email.to_string());
+        let id = user.id;
+        self.ids_by_email.insert(user.email.clone(), id);
+        self.ids_by_name.insert(user.name.clone(), id);
+        self.users.push(user);
+        id
+    }
+
+    /// Whether commits were added since the last update of the users.
+    pub fn has_changes(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Stats of every commit added so far.
+    pub fn repo_stats(&self) -> &RepoStats {
+        &self.repo_stats
+    }
+
+    /// Recomputes the stats of the users who got commits since the last
+    /// update.
+    pub fn update(&mut self) {
+        for user in self.users.iter_mut() {
+            if self.dirty.remove(&user.id)
+                && let Some(stats) = self.stats.get(&user.id)
+            {
+                stats.apply(user);
+            }
+        }
+        for source in self.sources.values_mut() {
+            source.update();
+        }
+    }
+
+    /// Every user seen so far, with their stats as of the last
+    /// [`update`](StatsAccumulator::update). Users are never removed or
+    /// reordered, so their indices stay valid as commits are added.
+    pub fn current_users(&self) -> &[UserInfo] {
+        &self.users
+    }
+
+    /// Returns every user seen so far with up-to-date stats.
+    pub fn users(&mut self) -> &[UserInfo] {
+        self.update();
+        &self.users
+    }
+
+    /// Users of each source seen so far, by name, with their stats as of the
+    /// last [`update`](StatsAccumulator::update).
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &[UserInfo])> {
+        self.sources
+            .iter()
+            .map(|(name, source)| (name.as_str(), source.current_users()))
+    }
+}
+
+pub(crate) fn utc_from_commit(commit: &Commit) -> Result<DateTime<Utc>> {
+    let invalid = || StatsError::InvalidTimestamp { oid: commit.id() };
+    let base_time = DateTime::from_timestamp(commit.time().seconds(), 0).ok_or_else(invalid)?;
     let timezone_offset = commit.time().offset_minutes();
-    let timezone = FixedOffset::east_opt(timezone_offset * 60).context("invalid offset")?;
+    let timezone = FixedOffset::east_opt(timezone_offset * 60).ok_or_else(invalid)?;
     Ok(DateTime::<Local>::from_naive_utc_and_offset(base_time.naive_utc(), timezone).to_utc())
 }
 
-fn get_lines_changed(repo: &Repository, commit: &Commit) -> anyhow::Result<(usize, usize)> {
-    let commit_tree = commit.tree()?;
-
-    let mut insertions = 0;
-    let mut deletions = 0;
+/// Lines changed per file by a commit.
+#[derive(Debug, Default)]
+pub(crate) struct FileChanges {
+    pub counted: Vec<FileChange>,
+    /// Changes to the binary files and the ones [`Churn`] leaves out.
+    pub excluded: Vec<FileChange>,
+    pub renames: usize,
+    pub copies: usize,
+}
 
-    if commit.parent_count() > 0 {
-        for i in 0..commit.parent_count() {
-            let parent = commit.parent(i)?;
-            let parent_tree = parent.tree()?;
+/// Lines changed per file by `commit`. Only the files matching one of `paths`
+/// are included, unless it's empty.
+pub(crate) fn get_file_changes(
+    repo: &Repository,
+    commit: &Commit,
+    paths: &[String],
+    churn: &Churn,
+) -> Result<FileChanges> {
+    diff_commit(repo, commit, paths, churn).map_err(|source| StatsError::DiffFailed {
+        oid: commit.id(),
+        source,
+    })
+}
 
-            let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?;
+fn diff_commit(
+    repo: &Repository,
+    commit: &Commit,
+    paths: &[String],
+    churn: &Churn,
+) -> Result<FileChanges, git2::Error> {
+    let commit_tree = commit.tree()?;
+    let ignored = churn.is_ignored(commit.id());
+    let mut options = churn.diff_options();
+    for path in paths {
+        options.pathspec(path);
+    }
 
-            let stats = diff.stats()?;
-            insertions += stats.insertions();
-            deletions += stats.deletions();
+    let mut changes = FileChanges::default();
+    let mut moved_lines = if ignored { None } else { churn.moved_lines() };
+    let mut add_diff = |mut diff: git2::Diff| -> Result<(), git2::Error> {
+        churn.find_similar(&mut diff)?;
+        for idx in 0..diff.deltas().len() {
+            let Some(patch) = Patch::from_diff(&diff, idx)? else {
+                continue;
+            };
+            let delta = patch.delta();
+            // Nothing an ignored commit does is credited to its author
+            match delta.status() {
+                Delta::Renamed if !ignored => changes.renames += 1,
+                Delta::Copied if !ignored => changes.copies += 1,
+                _ => (),
+            }
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (_, insertions, deletions) = patch.line_stats()?;
+
+            let files = if ignored || delta.flags().is_binary() || churn.is_excluded(&path) {
+                &mut changes.excluded
+            } else {
+                if let Some(moved_lines) = moved_lines.as_mut() {
+                    moved_lines.add(changes.counted.len(), &patch)?;
+                }
+                &mut changes.counted
+            };
+            // A commit is diffed once, against its only parent, and a path
+            // has a single delta in that diff
+            files.push(FileChange {
+                path,
+                lines_added: insertions,
+                lines_removed: deletions,
+            });
+        }
+        Ok(())
+    };
+
+    match commit.parent_count() {
+        0 => add_diff(repo.diff_tree_to_tree(None, Some(&commit_tree), Some(&mut options))?)?,
+        1 => {
+            let parent_tree = commit.parent(0)?.tree()?;
+            add_diff(repo.diff_tree_to_tree(
+                Some(&parent_tree),
+                Some(&commit_tree),
+                Some(&mut options),
+            )?)?;
         }
-    } else {
-        let diff = repo.diff_tree_to_tree(None, Some(&commit_tree), None)?;
+        // The changes of a merge are the ones of the commits it merges, which
+        // are counted on their own, like `git log --numstat` does
+        _ => (),
+    }
 
-        let stats = diff.stats()?;
-        insertions = stats.insertions();
-        deletions = stats.deletions();
+    if let Some(moved_lines) = moved_lines {
+        for (file, (added, removed)) in moved_lines.count() {
+            let change = &mut changes.counted[file];
+            change.lines_added -= added;
+            change.lines_removed -= removed;
+        }
     }
 
-    Ok((insertions, deletions))
+    Ok(changes)
 }
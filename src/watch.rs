@@ -0,0 +1,91 @@
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use git2::Repository;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::{Result, StatsError};
+
+/// Watches the refs of a repository for changes, e.g. new commits, fetches or
+/// checkouts. Uses inotify on Linux.
+pub struct RefWatcher {
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<()>,
+}
+
+impl RefWatcher {
+    /// Watches `HEAD` of `repo`, and `packed-refs` and everything under `refs`
+    /// in its common directory, which a linked worktree shares with the main
+    /// one.
+    pub fn new(repo: &Repository) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let git_dir = repo.path();
+        let common_dir = repo.commondir();
+        let refs_dir = common_dir.join("refs");
+        let filter_dir = refs_dir.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event
+                && is_ref_change(&event, &filter_dir)
+            {
+                let _ = sender.send(());
+            }
+        })
+        .map_err(|source| watch_error(common_dir, source))?;
+
+        // Git replaces HEAD and packed-refs by renaming a lock file over them, so
+        // the directories are watched rather than the files themselves
+        let mut dirs = vec![git_dir];
+        if common_dir != git_dir {
+            dirs.push(common_dir);
+        }
+        for dir in dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|source| watch_error(dir, source))?;
+        }
+        watcher
+            .watch(&refs_dir, RecursiveMode::Recursive)
+            .map_err(|source| watch_error(&refs_dir, source))?;
+
+        Ok(RefWatcher {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Whether a ref changed since the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(()) => changed = true,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return changed,
+            }
+        }
+    }
+}
+
+fn watch_error(path: &Path, source: notify::Error) -> StatsError {
+    StatsError::Watch {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn is_ref_change(event: &Event, refs_dir: &Path) -> bool {
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) {
+        return false;
+    }
+    event.paths.iter().any(|path| {
+        let is_lock = path.extension().is_some_and(|e| e == "lock");
+        let is_ref = path.starts_with(refs_dir)
+            || path
+                .file_name()
+                .is_some_and(|name| name == "HEAD" || name == "packed-refs");
+        is_ref && !is_lock
+    })
+}
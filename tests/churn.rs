@@ -0,0 +1,418 @@
+mod support;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use git_stats::{
+    Report, StatsError, StatsQuery,
+    churn::{self, ChurnOptions},
+    users::UserStats,
+};
+use git2::Oid;
+use support::{ALICE, BOB, Fixture};
+
+/// A commit adding two lines to each of `paths`.
+fn fixture(paths: &[&str]) -> Fixture {
+    let fixture = Fixture::new();
+    let mut commit = fixture.commit(ALICE, "2023-01-10T12:00:00Z");
+    for path in paths {
+        commit = commit.write(path, "1\n2\n");
+    }
+    commit.create();
+    fixture
+}
+
+fn run(fixture: &Fixture, churn: ChurnOptions) -> Report {
+    StatsQuery::new(&fixture.repo).churn(churn).run().unwrap()
+}
+
+/// Paths of the counted and of the excluded files of `report`.
+fn split(report: &Report) -> (Vec<&str>, Vec<&str>) {
+    (
+        sorted_paths(&report.repo_stats.files),
+        sorted_paths(&report.repo_stats.excluded_files),
+    )
+}
+
+fn sorted_paths<V>(files: &HashMap<String, V>) -> Vec<&str> {
+    let mut paths: Vec<&str> = files.keys().map(String::as_str).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn default_excludes() {
+    let fixture = fixture(&[
+        "Cargo.lock",
+        "web/package-lock.json",
+        "web/app.min.js",
+        "vendor/lib/lib.go",
+        "web/node_modules/left-pad/index.js",
+        "src/main.rs",
+        "src/vendor.rs",
+    ]);
+    let report = run(&fixture, ChurnOptions::default());
+    assert_eq!(
+        split(&report),
+        (
+            vec!["src/main.rs", "src/vendor.rs"],
+            vec![
+                "Cargo.lock",
+                "vendor/lib/lib.go",
+                "web/app.min.js",
+                "web/node_modules/left-pad/index.js",
+                "web/package-lock.json",
+            ]
+        )
+    );
+    let stats = &report.repo_stats;
+    assert_eq!(
+        (stats.total_lines_added, stats.excluded_lines_added),
+        (4, 10)
+    );
+    let alice = &report.users[0].stats;
+    assert_eq!(
+        (alice.total_lines_added, alice.excluded_lines_added),
+        (4, 10)
+    );
+    assert_eq!(report.users[0].stats.total_commits, 1);
+
+    let report = run(&fixture, ChurnOptions::include_all());
+    assert_eq!(report.repo_stats.files.len(), 7);
+    assert_eq!(report.repo_stats.excluded_lines_added, 0);
+}
+
+#[test]
+fn extra_excludes() {
+    let fixture = fixture(&["docs/logo.svg", "docs/index.md", "src/main.rs"]);
+    let report = run(
+        &fixture,
+        ChurnOptions {
+            excludes: vec!["*.svg".to_string()],
+            ..ChurnOptions::default()
+        },
+    );
+    assert_eq!(
+        split(&report),
+        (vec!["docs/index.md", "src/main.rs"], vec!["docs/logo.svg"])
+    );
+}
+
+#[test]
+fn gitattributes() {
+    let fixture = fixture(&[
+        "src/parser.rs",
+        "src/generated.rs",
+        "third_party/zlib/zlib.c",
+        "ext/lib.c",
+        "fixtures/data.txt",
+    ]);
+    fixture
+        .commit(ALICE, "2023-01-11T12:00:00Z")
+        .write(
+            ".gitattributes",
+            "src/generated.rs linguist-generated\n\
+             ext/** linguist-vendored=true\n\
+             third_party/** -linguist-vendored\n\
+             fixtures/* -diff\n",
+        )
+        .create();
+
+    let report = run(&fixture, ChurnOptions::default());
+    assert_eq!(
+        split(&report),
+        (
+            vec![".gitattributes", "src/parser.rs", "third_party/zlib/zlib.c"],
+            vec!["ext/lib.c", "fixtures/data.txt", "src/generated.rs"]
+        )
+    );
+
+    let report = run(
+        &fixture,
+        ChurnOptions {
+            attributes: false,
+            ..ChurnOptions::default()
+        },
+    );
+    assert_eq!(split(&report).1, ["third_party/zlib/zlib.c"]);
+}
+
+#[test]
+fn excluded_churn_of_each_file() {
+    let fixture = fixture(&["Cargo.lock"]);
+    fixture
+        .commit(ALICE, "2023-01-11T12:00:00Z")
+        .write("Cargo.lock", "1\n3\n4\n")
+        .create();
+    let report = run(&fixture, ChurnOptions::default());
+    let files: BTreeMap<&str, (usize, usize, usize)> = report
+        .repo_stats
+        .excluded_files
+        .iter()
+        .map(|(path, f)| (path.as_str(), (f.commits, f.lines_added, f.lines_removed)))
+        .collect();
+    assert_eq!(files, BTreeMap::from([("Cargo.lock", (2, 4, 1))]));
+    assert_eq!(report.repo_stats.total_commits, 2);
+    assert_eq!(report.repo_stats.total_lines_added, 0);
+}
+
+/// `a.txt` of ten lines is renamed to `b.txt`, with `changed` of its lines
+/// changed.
+fn renamed(changed: usize) -> Fixture {
+    let lines: Vec<String> = (1..=10).map(|n| format!("line {}\n", n)).collect();
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-10T12:00:00Z")
+        .write("a.txt", &lines.concat())
+        .create();
+    let mut lines = lines;
+    for line in lines.iter_mut().take(changed) {
+        *line = format!("changed {}", line);
+    }
+    fixture
+        .commit(BOB, "2023-01-11T12:00:00Z")
+        .rename("a.txt", "b.txt")
+        .write("b.txt", &lines.concat())
+        .create();
+    fixture
+}
+
+fn bob(report: &Report) -> &UserStats {
+    &report.users.iter().find(|u| u.name == "Bob").unwrap().stats
+}
+
+#[test]
+fn similarity_threshold() {
+    let options = |similarity_threshold| ChurnOptions {
+        similarity_threshold,
+        ..ChurnOptions::default()
+    };
+    let counts = |stats: &UserStats| {
+        (
+            stats.total_lines_added,
+            stats.total_lines_removed,
+            stats.renames,
+        )
+    };
+
+    let report = run(&renamed(3), options(Some(50)));
+    assert_eq!(counts(bob(&report)), (3, 3, 1));
+    assert_eq!(sorted_paths(&report.repo_stats.files), ["a.txt", "b.txt"]);
+
+    // Too different to be a rename
+    let report = run(&renamed(6), options(Some(50)));
+    assert_eq!(counts(bob(&report)), (10, 10, 0));
+    let report = run(&renamed(3), options(Some(90)));
+    assert_eq!(counts(bob(&report)), (10, 10, 0));
+
+    let report = run(&renamed(0), options(None));
+    assert_eq!(counts(bob(&report)), (10, 10, 0));
+}
+
+#[test]
+fn copies_of_changed_files() {
+    let lines: String = (1..=10).map(|n| format!("line {}\n", n)).collect();
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-10T12:00:00Z")
+        .write("a.txt", &lines)
+        .create();
+    fixture
+        .commit(BOB, "2023-01-11T12:00:00Z")
+        .write("a.txt", &format!("{}line 11\n", lines))
+        .write("b.txt", &lines)
+        .create();
+
+    let stats = bob(&run(&fixture, ChurnOptions::default())).clone();
+    assert_eq!(
+        (stats.total_lines_added, stats.copies, stats.renames),
+        (1, 1, 0)
+    );
+}
+
+/// Alice writes `main.rs`, Bob reindents it and Alice then changes a line.
+fn reformatted() -> (Fixture, Oid) {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-10T12:00:00Z")
+        .write("main.rs", "fn main() {\nrun();\nstop();\n}\n")
+        .create();
+    let reformat = fixture
+        .commit(BOB, "2023-01-11T12:00:00Z")
+        .write("main.rs", "fn main() {\n    run();\n    stop();\n}\n")
+        .create();
+    fixture
+        .commit(ALICE, "2023-01-12T12:00:00Z")
+        .write("main.rs", "fn main() {\n    run();\n}\n")
+        .create();
+    (fixture, reformat)
+}
+
+fn line_counts(report: &Report) -> BTreeMap<&str, (usize, usize, usize, usize)> {
+    report
+        .users
+        .iter()
+        .map(|u| {
+            let stats = &u.stats;
+            (
+                u.name.as_str(),
+                (
+                    stats.total_lines_added,
+                    stats.total_lines_removed,
+                    stats.excluded_lines_added,
+                    stats.excluded_lines_removed,
+                ),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn ignore_whitespace() {
+    let (fixture, _) = reformatted();
+    let report = run(&fixture, ChurnOptions::default());
+    assert_eq!(
+        line_counts(&report),
+        BTreeMap::from([("Alice", (4, 1, 0, 0)), ("Bob", (2, 2, 0, 0))])
+    );
+
+    let options = ChurnOptions {
+        ignore_whitespace: true,
+        ..ChurnOptions::default()
+    };
+    let report = run(&fixture, options);
+    assert_eq!(
+        line_counts(&report),
+        BTreeMap::from([("Alice", (4, 1, 0, 0)), ("Bob", (0, 0, 0, 0))])
+    );
+    assert_eq!(report.users.len(), 2);
+}
+
+#[test]
+fn ignored_revs() {
+    let (fixture, reformat) = reformatted();
+    let options = ChurnOptions {
+        ignored_revs: vec![reformat],
+        ..ChurnOptions::default()
+    };
+    let report = run(&fixture, options);
+    assert_eq!(
+        line_counts(&report),
+        BTreeMap::from([("Alice", (4, 1, 0, 0)), ("Bob", (0, 0, 2, 2))])
+    );
+    let bob = report.users.iter().find(|u| u.name == "Bob").unwrap();
+    assert_eq!(bob.stats.total_commits, 1);
+}
+
+#[test]
+fn ignored_revs_rename_nothing() {
+    let fixture = renamed(0);
+    let rename = fixture.repo.head().unwrap().target().unwrap();
+    let options = ChurnOptions {
+        ignored_revs: vec![rename],
+        ..ChurnOptions::default()
+    };
+    let report = run(&fixture, options);
+    let bob = bob(&report);
+    assert_eq!((bob.renames, bob.copies), (0, 0));
+    assert_eq!(bob.total_commits, 1);
+    assert_eq!((bob.total_lines_added, bob.total_lines_removed), (0, 0));
+}
+
+const MAIN: &str = "fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    println!(\"{}\", render(parse(&args[1])));
+}
+";
+const PARSE: &str = "fn parse(input: &str) -> u32 {
+    input.parse().unwrap()
+}
+";
+const TRIMMED_PARSE: &str = "fn parse(input: &str) -> u32 {
+    let input = input.trim();
+    input.parse().unwrap()
+}
+";
+const RENDER: &str = "fn render(value: u32) -> String {
+    format!(\"{value}\")
+}
+";
+const UTIL: &str = "mod util {
+    fn render(value: u32) -> String {
+        format!(\"{value}\")
+    }
+
+    pub fn version() -> &'static str {
+        env!(\"CARGO_PKG_VERSION\")
+    }
+
+    pub fn authors() -> &'static str {
+        env!(\"CARGO_PKG_AUTHORS\")
+    }
+}
+";
+
+/// Alice writes `lib.rs` with three functions. Bob then moves `main` as is
+/// to `main.rs`, moves `render` to a module of `util.rs`, which reindents it,
+/// and adds a line to `parse`.
+fn moved() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-10T12:00:00Z")
+        .write("lib.rs", &format!("{MAIN}\n{PARSE}\n{RENDER}"))
+        .create();
+    fixture
+        .commit(BOB, "2023-01-11T12:00:00Z")
+        .write("lib.rs", TRIMMED_PARSE)
+        .write("main.rs", MAIN)
+        .write("util.rs", UTIL)
+        .create();
+    fixture
+}
+
+#[test]
+fn ignore_moves() {
+    let fixture = moved();
+    let counts = |ignore_whitespace, ignore_moves| {
+        // The files are small enough for libgit2 to take `main.rs` for a copy
+        // of `lib.rs`, keep copies out of the way of the moves
+        let options = ChurnOptions {
+            similarity_threshold: None,
+            ignore_whitespace,
+            ignore_moves,
+            ..ChurnOptions::default()
+        };
+        let report = run(&fixture, options);
+        let bob = bob(&report);
+        (bob.total_lines_added, bob.total_lines_removed)
+    };
+
+    assert_eq!(counts(false, false), (18, 9));
+    // `main` is left out, `render` is reindented so its lines differ
+    assert_eq!(counts(false, true), (14, 5));
+    assert_eq!(counts(true, false), (18, 9));
+    // The blank lines between the functions are too short to be moves
+    assert_eq!(counts(true, true), (11, 2));
+}
+
+#[test]
+fn reads_ignore_revs_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join(".git-blame-ignore-revs");
+    let hash = "0123456789abcdef0123456789abcdef01234567";
+    fs::write(
+        &path,
+        format!("# Run rustfmt\n{hash}\n\n  {hash} # twice\r\n"),
+    )
+    .unwrap();
+    let oid = Oid::from_str(hash).unwrap();
+    assert_eq!(churn::read_ignore_revs(&path).unwrap(), [oid, oid]);
+
+    fs::write(&path, format!("{hash}\n0123abc\n")).unwrap();
+    let err = churn::read_ignore_revs(&path).unwrap_err();
+    assert!(matches!(&err, StatsError::IgnoreRevsFile { path: p, .. } if *p == path));
+    assert!(
+        err.to_string()
+            .ends_with("line 2: '0123abc' is not a full commit hash")
+    );
+}
@@ -0,0 +1,152 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use git_stats::{
+    config::Config,
+    tui::{
+        app::{App, View},
+        keymap::Action,
+        theme::Theme,
+        ui,
+    },
+};
+use ratatui::{Terminal, backend::TestBackend, style::Color};
+use tempfile::TempDir;
+
+/// The config parsed from the TOML `contents`, read from a file like the CLI
+/// does.
+fn config(contents: &str) -> Config {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, contents).unwrap();
+    Config::load(Some(&path)).unwrap()
+}
+
+#[test]
+fn selects_the_dark_theme_by_default() {
+    assert_eq!(Config::default().theme(None).unwrap(), Theme::dark());
+}
+
+#[test]
+fn selects_the_theme_of_the_config() {
+    let config = config("theme = \"light\"\n");
+    assert_eq!(config.theme(None).unwrap(), Theme::light());
+    // The command line wins over the config file
+    assert_eq!(
+        config.theme(Some("high-contrast")).unwrap(),
+        Theme::high_contrast()
+    );
+}
+
+#[test]
+fn light_theme_mutes_with_a_dark_color() {
+    // Gray text is hard to read on a light background
+    assert_eq!(Theme::light().muted, Color::DarkGray);
+}
+
+#[test]
+fn custom_theme_overrides_the_theme_it_extends() {
+    let config = config(
+        r##"
+theme = "mine"
+
+[themes.mine]
+extends = "light"
+border = "magenta"
+muted = "#808080"
+series = ["red", "green"]
+"##,
+    );
+    let theme = config.theme(None).unwrap();
+    assert_eq!(
+        theme,
+        Theme {
+            border: Color::Magenta,
+            muted: Color::Rgb(0x80, 0x80, 0x80),
+            series: vec![Color::Red, Color::Green],
+            ..Theme::light()
+        }
+    );
+}
+
+#[test]
+fn custom_theme_extends_the_dark_theme_by_default() {
+    let config = config("[themes.mine]\ntitle = \"cyan\"\n");
+    assert_eq!(
+        config.theme(Some("mine")).unwrap(),
+        Theme {
+            title: Color::Cyan,
+            ..Theme::dark()
+        }
+    );
+}
+
+#[test]
+fn rejects_unknown_themes() {
+    let config = config("[themes.mine]\nextends = \"mine\"\n");
+    let err = config.theme(Some("mine")).unwrap_err();
+    assert_eq!(err.to_string(), "Theme 'mine' extends itself");
+
+    let err = config.theme(Some("solarized")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unknown theme 'solarized', expected one of: dark, light, high-contrast, none, mine"
+    );
+
+    let config = self::config("[themes.mine]\nextends = \"solarized\"\n");
+    let err = config.theme(Some("mine")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Theme 'mine' extends unknown theme 'solarized', expected one of: dark, light, \
+         high-contrast, none"
+    );
+}
+
+#[test]
+fn rejects_unknown_config_keys() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[themes.mine]\nborders = \"red\"\n").unwrap();
+    assert!(Config::load(Some(&path)).is_err());
+}
+
+#[test]
+fn keys_of_the_config_replace_the_default_ones() {
+    let config = config("[keys]\nquit = [\"x\"]\n");
+    let keymap = config.keymap().unwrap();
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    assert_eq!(keymap.action(key(KeyCode::Char('x'))), Some(Action::Quit));
+    assert_eq!(keymap.action(key(KeyCode::Char('q'))), None);
+}
+
+#[test]
+fn rejects_keys_bound_to_two_actions() {
+    let config = config("[keys]\nquit = [\"q\", \"s\"]\n");
+    let err = config.keymap().unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "Invalid [keys] in the config file: Key 's' is bound to both 'quit' and 'sort'"
+    );
+
+    // Moving the other action out of the way is fine
+    let config = self::config("[keys]\nquit = [\"s\"]\nsort = [\"o\"]\n");
+    assert!(config.keymap().is_ok());
+}
+
+#[test]
+fn every_pane_is_drawn_with_the_theme() {
+    let theme = Theme {
+        border: Color::Magenta,
+        title: Color::Cyan,
+        ..Theme::dark()
+    };
+    let mut app = App::new(Vec::new()).with_theme(theme);
+    app.view = View::Summary;
+    let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+    terminal.draw(|f| ui::render(f, &mut app)).unwrap();
+
+    // The summary pane starts below the tabs
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer[(0, 1)].symbol(), "┌");
+    assert_eq!(buffer[(0, 1)].fg, Color::Magenta);
+    assert_eq!(buffer[(1, 1)].symbol(), "S");
+    assert_eq!(buffer[(1, 1)].fg, Color::Cyan);
+}
@@ -0,0 +1,187 @@
+mod support;
+
+use std::process::Command;
+
+use git_stats::{
+    StatsError, StatsQuery,
+    analysis::{Analysis, AnalysisEvent},
+    repository,
+    users::get_users,
+};
+use support::{ALICE, Fixture};
+
+fn git_stats(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_git-stats"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn one_commit() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-01T12:00:00Z")
+        .write("a.txt", "1\n")
+        .create();
+    fixture
+}
+
+#[test]
+fn missing_repository() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("nope");
+    assert!(matches!(
+        repository::open(&path),
+        Err(StatsError::RepoNotFound { path: p, .. }) if p == path
+    ));
+
+    let (code, stderr) = git_stats(&["--repo", path.to_str().unwrap()]);
+    assert_eq!(code, Some(3));
+    assert!(stderr.starts_with("Error: No git repository found at"));
+}
+
+#[test]
+fn bad_revspec() {
+    let fixture = one_commit();
+    let result = StatsQuery::new(&fixture.repo).revspec("nope").run();
+    assert!(matches!(
+        result,
+        Err(StatsError::BadRevspec { revspec, .. }) if revspec == "nope"
+    ));
+    let events: Vec<AnalysisEvent> = Analysis::spawn(fixture.path(), "nope").collect();
+    assert!(matches!(
+        events.as_slice(),
+        [AnalysisEvent::Failed(StatsError::BadRevspec { revspec, .. })] if revspec == "nope"
+    ));
+
+    let (code, stderr) = git_stats(&[
+        "--repo",
+        fixture.path().to_str().unwrap(),
+        "--branch",
+        "nope",
+    ]);
+    assert_eq!(code, Some(4));
+    assert!(stderr.starts_with("Error: Unknown revision 'nope'\n"));
+}
+
+#[test]
+fn empty_repository() {
+    let fixture = Fixture::new();
+    assert!(matches!(
+        get_users(&fixture.repo),
+        Err(StatsError::EmptyRepository)
+    ));
+    assert!(matches!(
+        StatsQuery::new(&fixture.repo).run(),
+        Err(StatsError::EmptyRepository)
+    ));
+
+    for tui in [false, true] {
+        let mut args = vec!["--repo", fixture.path().to_str().unwrap()];
+        if tui {
+            args.push("--tui");
+        }
+        let (code, stderr) = git_stats(&args);
+        assert_eq!(code, Some(5));
+        assert!(stderr.starts_with("Error: The repository has no commits yet\n"));
+    }
+}
+
+#[test]
+fn unborn_head() {
+    let fixture = one_commit();
+    fixture.repo.set_head("refs/heads/next").unwrap();
+    assert!(matches!(
+        StatsQuery::new(&fixture.repo).run(),
+        Err(StatsError::UnbornHead { branch }) if branch == "next"
+    ));
+    assert_eq!(
+        StatsQuery::new(&fixture.repo)
+            .revspec("main")
+            .run()
+            .unwrap()
+            .users
+            .len(),
+        1
+    );
+
+    let (code, stderr) = git_stats(&["--repo", fixture.path().to_str().unwrap()]);
+    assert_eq!(code, Some(9));
+    assert!(stderr.starts_with("Error: HEAD points to 'next', which has no commits yet\n"));
+}
+
+#[test]
+fn shallow_clone() {
+    let fixture = one_commit();
+    fixture
+        .commit(ALICE, "2023-02-01T12:00:00Z")
+        .write("a.txt", "1\n2\n")
+        .create();
+    let boundary = fixture
+        .commit(ALICE, "2023-03-01T12:00:00Z")
+        .write("a.txt", "1\n2\n3\n")
+        .create();
+    fixture
+        .commit(ALICE, "2023-04-01T12:00:00Z")
+        .write("a.txt", "1\n2\n3\n4\n")
+        .create();
+    let dir = tempfile::TempDir::new().unwrap();
+    let status = Command::new("git")
+        .args(["clone", "--quiet", "--depth", "2"])
+        .arg(format!("file://{}", fixture.path().display()))
+        .arg(dir.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let clone = repository::open(dir.path()).unwrap();
+    let report = StatsQuery::new(&clone).run().unwrap();
+    assert_eq!(report.repo_stats.total_commits, 2);
+    let partial = report.partial.unwrap();
+    assert_eq!(partial.commits, [boundary]);
+    assert_eq!(partial.date.to_rfc3339(), "2023-03-01T12:00:00+00:00");
+    assert_eq!(StatsQuery::new(&fixture.repo).run().unwrap().partial, None);
+
+    let (code, stderr) = git_stats(&["--repo", dir.path().to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    assert!(stderr.starts_with(
+        "Warning: the repository is a shallow clone, commits before 2023-03-01 are missing"
+    ));
+}
+
+#[test]
+fn bad_ignore_revs_file() {
+    let fixture = one_commit();
+    let path = fixture.path().join(".git-blame-ignore-revs");
+    std::fs::write(&path, "HEAD\n").unwrap();
+
+    let (code, stderr) = git_stats(&[
+        "--repo",
+        fixture.path().to_str().unwrap(),
+        "--ignore-revs-file",
+        path.to_str().unwrap(),
+    ]);
+    assert_eq!(code, Some(10));
+    assert!(stderr.starts_with("Error: Failed to read the commits to ignore from"));
+}
+
+#[test]
+fn missing_configured_ignore_revs_file() {
+    let fixture = one_commit();
+    let mut config = fixture.repo.config().unwrap();
+    config
+        .set_str("blame.ignoreRevsFile", ".git-blame-ignore-revs")
+        .unwrap();
+
+    let (code, stderr) = git_stats(&["--repo", fixture.path().to_str().unwrap()]);
+    assert_eq!(code, Some(0));
+    let missing = fixture.path().join(".git-blame-ignore-revs");
+    assert!(stderr.starts_with(&format!(
+        "Warning: {} set by blame.ignoreRevsFile does not exist",
+        missing.display()
+    )));
+}
@@ -0,0 +1,119 @@
+mod support;
+
+use std::collections::BTreeMap;
+
+use git_stats::{
+    StatsQuery,
+    export::{Export, ExportFormat, UserSummary},
+    languages::{self, LanguageStats, OTHER, language_of},
+};
+use support::{ALICE, BOB, Fixture};
+
+#[test]
+fn classifies_by_filename_then_extension() {
+    assert_eq!(language_of("src/main.rs"), "Rust");
+    assert_eq!(language_of("web/App.TSX"), "TypeScript");
+    assert_eq!(language_of("include/stats.h"), "C");
+    assert_eq!(language_of("docker/Dockerfile"), "Dockerfile");
+    assert_eq!(language_of("Makefile"), "Makefile");
+    assert_eq!(language_of("CMakeLists.txt"), "CMake");
+    assert_eq!(language_of("notes.txt"), OTHER);
+    assert_eq!(language_of("LICENSE"), OTHER);
+    assert_eq!(language_of(".gitignore"), OTHER);
+}
+
+/// Alice changes Rust and Markdown files, Bob a Makefile and a Rust file.
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-10T12:00:00Z")
+        .write("src/main.rs", "fn main() {\n    run();\n}\n")
+        .write("README.md", "# Readme\n")
+        .create();
+    fixture
+        .commit(BOB, "2023-01-11T12:00:00Z")
+        .write("Makefile", "all:\n\tcargo build\n")
+        .write("src/main.rs", "fn main() {}\n")
+        .create();
+    fixture
+        .commit(ALICE, "2023-01-12T12:00:00Z")
+        .write("src/lib.rs", "pub fn run() {}\n")
+        .create();
+    fixture
+}
+
+fn lines(lines_added: usize, lines_removed: usize) -> LanguageStats {
+    LanguageStats {
+        lines_added,
+        lines_removed,
+    }
+}
+
+#[test]
+fn attributes_line_changes_per_language() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo).run().unwrap();
+    let languages: BTreeMap<&str, _> = report
+        .users
+        .iter()
+        .map(|u| (u.name.as_str(), u.stats.languages.clone()))
+        .collect();
+    assert_eq!(
+        languages,
+        BTreeMap::from([
+            (
+                "Alice",
+                BTreeMap::from([("Markdown", lines(1, 0)), ("Rust", lines(4, 0))])
+            ),
+            (
+                "Bob",
+                BTreeMap::from([("Makefile", lines(2, 0)), ("Rust", lines(1, 3))])
+            ),
+        ])
+    );
+}
+
+#[test]
+fn distribution_is_biggest_share_first() {
+    let stats = BTreeMap::from([
+        ("Markdown", lines(5, 5)),
+        ("Rust", lines(20, 10)),
+        ("C", lines(10, 0)),
+    ]);
+    assert_eq!(
+        languages::distribution(&stats),
+        [("Rust", 60.0), ("C", 20.0), ("Markdown", 20.0)]
+    );
+    assert_eq!(languages::distribution(&BTreeMap::new()), []);
+}
+
+fn export(export: &Export, format: ExportFormat) -> String {
+    let mut out = Vec::new();
+    export.write(format, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn exports_languages() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo).run().unwrap();
+    let alice = report.users.iter().find(|u| u.name == "Alice").unwrap();
+    let summary = UserSummary::new(alice, report.repo_stats.total_commits);
+
+    let markdown = export(&Export::User(summary.clone()), ExportFormat::Markdown);
+    assert!(markdown.ends_with(
+        "## Languages\n\n\
+         | Language | Lines added | Lines removed | Share |\n\
+         | --- | --- | --- | --- |\n\
+         | Rust | 4 | 0 | 80.00% |\n\
+         | Markdown | 1 | 0 | 20.00% |\n"
+    ));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&export(&Export::User(summary.clone()), ExportFormat::Json)).unwrap();
+    assert_eq!(json["languages"][0]["language"], "Rust");
+    assert_eq!(json["languages"][0]["share"], 80.0);
+
+    let csv = export(&Export::Users(vec![summary]), ExportFormat::Csv);
+    assert!(!csv.contains("Rust"));
+}
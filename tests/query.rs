@@ -0,0 +1,174 @@
+mod support;
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate};
+use git_stats::{
+    Report, StatsQuery,
+    timeseries::Granularity,
+    users::{UserStats, get_users, update_user_stats},
+};
+use git2::Mailmap;
+use support::{ALICE, BOB, CAROL, Fixture};
+
+/// Alice, Bob and Alice again change `src/` and `docs/` in January, February
+/// and April 2023. The tip is tagged `v1` after Bob's commit.
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-10T12:00:00Z")
+        .write("src/main.rs", "fn main() {}\n")
+        .write("docs/README.md", "# Docs\n")
+        .create();
+    let v1 = fixture
+        .commit(BOB, "2023-02-10T12:00:00Z")
+        .write("docs/README.md", "# Docs\n\nMore\n")
+        .create();
+    fixture
+        .repo
+        .tag_lightweight("v1", &fixture.repo.find_object(v1, None).unwrap(), false)
+        .unwrap();
+    fixture
+        .commit(ALICE, "2023-04-10T12:00:00Z")
+        .write("src/main.rs", "fn main() {\n    run();\n}\n")
+        .create();
+    fixture
+}
+
+/// Commits and lines added of every author of `report`, by name.
+fn totals(report: &Report) -> BTreeMap<&str, (u32, usize)> {
+    report
+        .users
+        .iter()
+        .map(|u| {
+            (
+                u.name.as_str(),
+                (u.stats.total_commits, u.stats.total_lines_added),
+            )
+        })
+        .collect()
+}
+
+fn month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+}
+
+#[test]
+fn matches_get_users_and_update_user_stats() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo).run().unwrap();
+
+    let mut users = get_users(&fixture.repo).unwrap();
+    update_user_stats(&mut users, &fixture.repo, "HEAD").unwrap();
+    let expected: BTreeMap<String, UserStats> =
+        users.into_iter().map(|u| (u.name, u.stats)).collect();
+    let stats: BTreeMap<String, UserStats> = report
+        .users
+        .into_iter()
+        .map(|u| (u.name, u.stats))
+        .collect();
+    assert_eq!(stats, expected);
+    assert_eq!(report.repo_stats.total_commits, 3);
+}
+
+#[test]
+fn activity_covers_every_period() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo).run().unwrap();
+    assert_eq!(report.activity.granularity, Granularity::Month);
+    assert_eq!(
+        report.activity.commits,
+        [
+            (month(2023, 1), 1),
+            (month(2023, 2), 1),
+            (month(2023, 3), 0),
+            (month(2023, 4), 1)
+        ]
+    );
+    assert_eq!(
+        report.activity.line_changes,
+        [
+            (month(2023, 1), 2),
+            (month(2023, 2), 2),
+            (month(2023, 3), 0),
+            (month(2023, 4), 4)
+        ]
+    );
+
+    let yearly = StatsQuery::new(&fixture.repo)
+        .granularity(Granularity::Year)
+        .run()
+        .unwrap();
+    assert_eq!(yearly.activity.commits, [(month(2023, 1), 3)]);
+}
+
+#[test]
+fn revspec_limits_the_history() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo).revspec("v1").run().unwrap();
+    assert_eq!(
+        totals(&report),
+        BTreeMap::from([("Alice", (1, 2)), ("Bob", (1, 2))])
+    );
+
+    let report = StatsQuery::new(&fixture.repo)
+        .revspec("v1..main")
+        .run()
+        .unwrap();
+    assert_eq!(totals(&report), BTreeMap::from([("Alice", (1, 3))]));
+}
+
+#[test]
+fn since_leaves_out_older_commits() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo)
+        .since(
+            DateTime::parse_from_rfc3339("2023-02-10T12:00:00Z")
+                .unwrap()
+                .to_utc(),
+        )
+        .run()
+        .unwrap();
+    assert_eq!(
+        totals(&report),
+        BTreeMap::from([("Alice", (1, 3)), ("Bob", (1, 2))])
+    );
+}
+
+#[test]
+fn paths_limit_the_changes() {
+    let fixture = fixture();
+    let report = StatsQuery::new(&fixture.repo).paths(["src"]).run().unwrap();
+    assert_eq!(totals(&report), BTreeMap::from([("Alice", (2, 4))]));
+    assert_eq!(
+        report.repo_stats.files.keys().collect::<Vec<_>>(),
+        ["src/main.rs"]
+    );
+}
+
+#[test]
+fn identity_merges_authors() {
+    let fixture = fixture();
+    fixture
+        .commit(CAROL, "2023-05-01T12:00:00Z")
+        .write("src/lib.rs", "\n")
+        .create();
+    let mut mailmap = Mailmap::new().unwrap();
+    mailmap
+        .add_entry(
+            Some("Bob"),
+            Some("bob@example.com"),
+            None,
+            "carol@example.com",
+        )
+        .unwrap();
+
+    let report = StatsQuery::new(&fixture.repo)
+        .identity(mailmap)
+        .run()
+        .unwrap();
+    assert_eq!(
+        totals(&report),
+        BTreeMap::from([("Alice", (2, 5)), ("Bob", (2, 3))])
+    );
+}
@@ -0,0 +1,137 @@
+mod support;
+
+use std::path::Path;
+use std::process::Command;
+
+use git_stats::{
+    StatsError, StatsQuery,
+    analysis::{Analysis, AnalysisEvent},
+    repository,
+    watch::RefWatcher,
+};
+use git2::build::RepoBuilder;
+use support::{ALICE, BOB, Fixture};
+
+fn fixture() -> Fixture {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-01T12:00:00Z")
+        .write("src/main.rs", "fn main() {}\n")
+        .create();
+    fixture
+        .commit(BOB, "2023-01-02T12:00:00Z")
+        .write("README.md", "# Readme\n")
+        .create();
+    fixture
+}
+
+fn total_commits(repo: &git2::Repository) -> usize {
+    StatsQuery::new(repo)
+        .run()
+        .unwrap()
+        .repo_stats
+        .total_commits
+}
+
+fn git_stats_code(args: &[&str]) -> Option<i32> {
+    git_stats_code_in(&std::env::current_dir().unwrap(), args)
+}
+
+/// Exit code of git-stats run from `dir`.
+fn git_stats_code_in(dir: &Path, args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_git-stats"))
+        .args(args)
+        .current_dir(dir)
+        .env_remove("GIT_DIR")
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn discovers_from_a_subdirectory() {
+    let fixture = fixture();
+    let subdir = fixture.path().join("src/deep");
+    std::fs::create_dir_all(&subdir).unwrap();
+
+    let repo = repository::discover(&subdir).unwrap();
+    assert_eq!(repo.path(), fixture.repo.path());
+    assert_eq!(total_commits(&repo), 2);
+    assert!(matches!(
+        repository::open(&subdir),
+        Err(StatsError::RepoNotFound { .. })
+    ));
+    assert_eq!(
+        git_stats_code(&["--repo", subdir.to_str().unwrap()]),
+        Some(0)
+    );
+}
+
+#[test]
+fn opens_a_bare_repository() {
+    let fixture = fixture();
+    let dir = tempfile::TempDir::new().unwrap();
+    let bare_path = dir.path().join("mirror.git");
+    RepoBuilder::new()
+        .bare(true)
+        .clone(&format!("file://{}", fixture.path().display()), &bare_path)
+        .unwrap();
+
+    for repo in [
+        repository::discover(&bare_path).unwrap(),
+        repository::open_git_dir(&bare_path).unwrap(),
+    ] {
+        assert!(repo.is_bare());
+        assert_eq!(total_commits(&repo), 2);
+    }
+    let bare_path = bare_path.to_str().unwrap();
+    assert_eq!(git_stats_code(&["--repo", bare_path]), Some(0));
+    assert_eq!(git_stats_code(&["--git-dir", bare_path]), Some(0));
+}
+
+#[test]
+fn opens_a_linked_worktree() {
+    let fixture = fixture();
+    let dir = tempfile::TempDir::new().unwrap();
+    let worktree_path = dir.path().join("wt");
+    fixture.repo.worktree("wt", &worktree_path, None).unwrap();
+
+    let repo = repository::discover(&worktree_path).unwrap();
+    assert!(repo.is_worktree());
+    assert_eq!(repo.commondir(), fixture.repo.path());
+    assert_eq!(total_commits(&repo), 2);
+    assert!(RefWatcher::new(&repo).is_ok());
+
+    let events: Vec<AnalysisEvent> = Analysis::spawn(repo.path(), "HEAD").collect();
+    let commits = events
+        .iter()
+        .filter(|e| matches!(e, AnalysisEvent::Commit(_)))
+        .count();
+    assert_eq!(commits, 2);
+    assert_eq!(events.last(), Some(&AnalysisEvent::Finished));
+}
+
+#[test]
+fn git_dir_is_not_searched() {
+    let fixture = fixture();
+    assert!(matches!(
+        repository::open_git_dir(fixture.path()),
+        Err(StatsError::RepoNotFound { .. })
+    ));
+    let repo = repository::open_git_dir(fixture.path().join(".git")).unwrap();
+    assert_eq!(total_commits(&repo), 2);
+
+    let path = fixture.path().to_str().unwrap();
+    assert_eq!(git_stats_code(&["--git-dir", path]), Some(3));
+    // Relative to the current directory, like `git --git-dir`, not to --repo
+    assert_eq!(
+        git_stats_code_in(fixture.path(), &["--git-dir", ".git"]),
+        Some(0)
+    );
+    let elsewhere = tempfile::TempDir::new().unwrap();
+    assert_eq!(
+        git_stats_code_in(elsewhere.path(), &["--repo", path, "--git-dir", ".git"]),
+        Some(3)
+    );
+}
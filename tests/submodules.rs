@@ -0,0 +1,234 @@
+mod support;
+
+use std::time::Duration;
+
+use git_stats::{
+    StatsQuery,
+    analysis::{Analysis, AnalysisEvent},
+    churn::ChurnOptions,
+    submodules,
+    tui::{
+        app::{App, View},
+        ui,
+    },
+    users::UserInfo,
+};
+use git2::Oid;
+use ratatui::{Terminal, backend::TestBackend};
+use support::{ALICE, BOB, CAROL, Fixture};
+
+/// A library with two commits by Bob and one by Carol, added as the
+/// submodule `libs/lib` of an app with two commits by Alice. Bob's last commit
+/// comes after the one pinned by the app.
+fn fixtures() -> (Fixture, Fixture) {
+    let lib = Fixture::new();
+    lib.commit(BOB, "2023-01-10T12:00:00Z")
+        .write("lib.rs", "pub fn a() {}\n")
+        .create();
+    lib.commit(CAROL, "2023-02-10T12:00:00Z")
+        .write("lib.rs", "pub fn a() {}\npub fn b() {}\n")
+        .create();
+
+    let app = Fixture::new();
+    app.commit(ALICE, "2023-01-15T12:00:00Z")
+        .write("main.rs", "fn main() {}\n")
+        .create();
+    app.add_submodule("libs/lib", &lib, ALICE, "2023-03-01T12:00:00Z");
+
+    lib.commit(BOB, "2023-04-10T12:00:00Z")
+        .write("lib.rs", "pub fn c() {}\n")
+        .create();
+    (app, lib)
+}
+
+/// The commit `revspec` of `fixture`.
+fn commit(fixture: &Fixture, revspec: &str) -> Oid {
+    fixture.repo.revparse_single(revspec).unwrap().id()
+}
+
+#[test]
+fn opens_the_submodules_at_their_pinned_commit() {
+    let (app, lib) = fixtures();
+    let submodules = submodules::open_submodules(&app.repo, commit(&app, "HEAD")).unwrap();
+    assert_eq!(submodules.repos.len(), 1);
+    assert!(submodules.skipped.is_empty());
+    let submodule = &submodules.repos[0];
+    assert_eq!(submodule.name, "libs/lib");
+    assert_eq!(submodule.path.to_str(), Some("libs/lib"));
+
+    let pinned = commit(&lib, "HEAD~1");
+    assert_eq!(submodule.pinned, Some(pinned));
+    assert_eq!(submodule.history().start, Some(pinned));
+    assert_eq!(submodule.history().name, Some("libs/lib"));
+    assert_eq!(submodule.warning, None);
+}
+
+#[test]
+fn pins_the_submodules_at_the_analyzed_commit() {
+    let (app, lib) = fixtures();
+    app.commit(ALICE, "2023-05-01T12:00:00Z")
+        .message("Bump lib")
+        .pin("libs/lib", commit(&lib, "HEAD"))
+        .on("bump")
+        .create();
+
+    // `HEAD` still pins the previous commit of the library
+    let submodules = submodules::open_submodules(&app.repo, commit(&app, "bump")).unwrap();
+    assert_eq!(submodules.repos[0].pinned, Some(commit(&lib, "HEAD")));
+
+    // The submodule didn't exist yet
+    let submodules = submodules::open_submodules(&app.repo, commit(&app, "HEAD~1")).unwrap();
+    assert!(submodules.repos.is_empty());
+}
+
+#[test]
+fn reports_the_submodules_that_are_not_analyzed_as_pinned() {
+    let (app, _lib) = fixtures();
+    // The library has no such commit
+    let missing = commit(&app, "HEAD~1");
+    app.commit(ALICE, "2023-05-01T12:00:00Z")
+        .pin("libs/lib", missing)
+        .create();
+
+    let submodules = submodules::open_submodules(&app.repo, commit(&app, "HEAD")).unwrap();
+    let submodule = &submodules.repos[0];
+    assert_eq!(submodule.history().start, None);
+    let report = StatsQuery::new(&app.repo)
+        .include(submodule.history())
+        .run()
+        .unwrap();
+    let reports = submodules::submodule_reports(&submodules, &report);
+    assert_eq!(
+        reports[0].warning,
+        Some(format!(
+            "pinned commit {missing} is not available, analyzed from its HEAD"
+        ))
+    );
+    // Analyzed from the `HEAD` of the library instead
+    assert_eq!(reports[0].users.len(), 2);
+}
+
+#[test]
+fn tags_the_commits_of_each_submodule() {
+    let (app, _lib) = fixtures();
+    let submodules = submodules::open_submodules(&app.repo, commit(&app, "HEAD")).unwrap();
+    let query = StatsQuery::new(&app.repo).include(submodules.repos[0].history());
+
+    let sources: Vec<(String, Option<String>)> = query
+        .walk()
+        .unwrap()
+        .map(|record| record.unwrap().unwrap())
+        .map(|record| (record.author_name, record.source))
+        .collect();
+    let lib = Some("libs/lib".to_string());
+    assert_eq!(
+        sources,
+        [
+            ("Alice".to_string(), None),
+            ("Alice".to_string(), None),
+            ("Carol".to_string(), lib.clone()),
+            ("Bob".to_string(), lib),
+        ]
+    );
+}
+
+#[test]
+fn reports_each_submodule_from_the_same_walk() {
+    let (app, _lib) = fixtures();
+    let submodules = submodules::open_submodules(&app.repo, commit(&app, "HEAD")).unwrap();
+    let report = StatsQuery::new(&app.repo)
+        .include(submodules.repos[0].history())
+        .run()
+        .unwrap();
+
+    // The whole project counts the commits of the app and the library
+    let commits = |users: &[UserInfo]| -> Vec<(String, u32)> {
+        users
+            .iter()
+            .map(|u| (u.name.clone(), u.stats.total_commits))
+            .collect()
+    };
+    assert_eq!(
+        commits(&report.users),
+        [
+            ("Alice".to_string(), 2),
+            ("Carol".to_string(), 1),
+            ("Bob".to_string(), 1)
+        ]
+    );
+    assert_eq!(report.repo_stats.total_commits, 4);
+
+    let reports = submodules::submodule_reports(&submodules, &report);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].name, "libs/lib");
+    assert_eq!(
+        commits(&reports[0].users),
+        [("Carol".to_string(), 1), ("Bob".to_string(), 1)]
+    );
+    let carol = &reports[0].users[0].stats;
+    assert_eq!((carol.total_lines_added, carol.total_lines_removed), (1, 0));
+}
+
+#[test]
+fn analysis_tags_the_commits_of_each_submodule() {
+    let (app, _lib) = fixtures();
+    let analysis = Analysis::spawn_recursive(app.path(), "HEAD", ChurnOptions::default());
+    assert!(analysis.recurses_submodules());
+
+    let events: Vec<AnalysisEvent> = analysis.collect();
+    assert!(matches!(
+        events[0],
+        AnalysisEvent::Started {
+            total_commits: 4,
+            ..
+        }
+    ));
+    let sources: Vec<Option<&str>> = events
+        .iter()
+        .filter_map(|event| match event {
+            AnalysisEvent::Commit(record) => Some(record.source.as_deref()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sources, [None, None, Some("libs/lib"), Some("libs/lib")]);
+    assert_eq!(events.last(), Some(&AnalysisEvent::Finished));
+}
+
+#[test]
+fn tui_shows_the_contributors_of_each_submodule() {
+    let (fixture, _lib) = fixtures();
+    let mut app = App::with_analysis(Analysis::spawn_recursive(
+        fixture.path(),
+        "HEAD",
+        ChurnOptions::default(),
+    ));
+    while app.is_loading() {
+        std::thread::sleep(Duration::from_millis(10));
+        app.on_tick();
+    }
+
+    let submodules: Vec<(&str, Vec<&str>)> = app
+        .submodules()
+        .map(|(name, users)| (name, users.iter().map(|u| u.name.as_str()).collect()))
+        .collect();
+    assert_eq!(submodules, [("libs/lib", vec!["Carol", "Bob"])]);
+
+    app.view = View::Summary;
+    let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+    terminal.draw(|f| ui::render(f, &mut app)).unwrap();
+    let text: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    for expected in [
+        "Submodules",
+        "libs/lib",
+        "2 commits by 2 authors",
+        "Carol - 1 commits",
+    ] {
+        assert!(text.contains(expected), "{expected:?} is not shown");
+    }
+}
@@ -0,0 +1,295 @@
+//! Synthetic git repositories for the integration tests.
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use chrono::DateTime;
+use git2::{Commit, Index, IndexEntry, IndexTime, Oid, Repository, Signature, Time};
+use tempfile::TempDir;
+
+/// Someone who writes commits.
+#[derive(Debug, Clone, Copy)]
+pub struct Author {
+    pub name: &'static str,
+    pub email: &'static str,
+}
+
+pub const ALICE: Author = Author {
+    name: "Alice",
+    email: "alice@example.com",
+};
+pub const BOB: Author = Author {
+    name: "Bob",
+    email: "bob@example.com",
+};
+pub const CAROL: Author = Author {
+    name: "Carol",
+    email: "carol@example.com",
+};
+pub const DAN: Author = Author {
+    name: "Dan",
+    email: "dan@example.com",
+};
+pub const ERIN: Author = Author {
+    name: "Erin",
+    email: "erin@example.com",
+};
+pub const FRANK: Author = Author {
+    name: "Frank",
+    email: "frank@example.com",
+};
+
+/// A repository in a temporary directory, removed when dropped. Commits are
+/// created straight in the object database, there is no working tree.
+pub struct Fixture {
+    pub repo: Repository,
+    dir: TempDir,
+}
+
+impl Fixture {
+    /// An empty repository, with `HEAD` pointing to the unborn `main` branch.
+    pub fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+        Fixture { repo, dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Starts a commit by `author` at `time`, an RFC 3339 timestamp such as
+    /// `2023-03-01T12:00:00+02:00`. It goes on top of `HEAD` unless
+    /// [`CommitBuilder::on`] says otherwise.
+    pub fn commit<'a>(&'a self, author: Author, time: &str) -> CommitBuilder<'a> {
+        CommitBuilder {
+            fixture: self,
+            author,
+            time: parse_time(time),
+            message: "Change".to_string(),
+            co_authors: Vec::new(),
+            branch: None,
+            merge: None,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Creates the branch `name` at `target`.
+    pub fn branch(&self, name: &str, target: Oid) {
+        let commit = self.repo.find_commit(target).unwrap();
+        self.repo.branch(name, &commit, false).unwrap();
+    }
+
+    /// Adds `submodule` at `path` in a commit by `author` at `time` on top of
+    /// `HEAD`, pinned at the `HEAD` of `submodule`. The submodule is cloned in
+    /// the working tree, like `git submodule add` does.
+    pub fn add_submodule(
+        &self,
+        path: &str,
+        submodule: &Fixture,
+        author: Author,
+        time: &str,
+    ) -> Oid {
+        let repo = &self.repo;
+        let parent = self.tip("HEAD");
+        // The gitlink and `.gitmodules` are staged in the index of the
+        // repository, which must hold the files of `HEAD`
+        let mut index = repo.index().unwrap();
+        if let Some(parent) = &parent {
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+        index.write().unwrap();
+
+        let url = submodule.path().to_str().unwrap();
+        let mut added = repo.submodule(url, Path::new(path), true).unwrap();
+        added.clone(None).unwrap();
+        added.add_finalize().unwrap();
+
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let signature = Signature::new(author.name, author.email, &parse_time(time)).unwrap();
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &format!("Add {}", path),
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    fn tip(&self, reference: &str) -> Option<Commit<'_>> {
+        self.repo
+            .find_reference(reference)
+            .and_then(|r| r.resolve())
+            .ok()
+            .and_then(|r| r.peel_to_commit().ok())
+    }
+}
+
+enum Change {
+    Write(String, Vec<u8>),
+    Remove(String),
+    Rename(String, String),
+    Pin(String, Oid),
+}
+
+/// A commit being scripted, see [`Fixture::commit`].
+pub struct CommitBuilder<'a> {
+    fixture: &'a Fixture,
+    author: Author,
+    time: Time,
+    message: String,
+    co_authors: Vec<Author>,
+    branch: Option<String>,
+    merge: Option<Oid>,
+    changes: Vec<Change>,
+}
+
+impl CommitBuilder<'_> {
+    /// Message of the commit, `Change` by default.
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    /// Adds a `Co-authored-by` trailer to the message.
+    pub fn co_author(mut self, author: Author) -> Self {
+        self.co_authors.push(author);
+        self
+    }
+
+    /// Commits on top of the branch `name` rather than `HEAD`.
+    pub fn on(mut self, name: &str) -> Self {
+        self.branch = Some(name.to_string());
+        self
+    }
+
+    /// Makes a merge of `other` into the branch. The tree starts as the merge
+    /// of both parents, which must not conflict.
+    pub fn merge(mut self, other: Oid) -> Self {
+        self.merge = Some(other);
+        self
+    }
+
+    /// Sets the contents of the text file at `path`.
+    pub fn write(self, path: &str, contents: &str) -> Self {
+        self.write_bytes(path, contents.as_bytes())
+    }
+
+    /// Sets the contents of the file at `path`.
+    pub fn write_bytes(mut self, path: &str, contents: &[u8]) -> Self {
+        self.changes
+            .push(Change::Write(path.to_string(), contents.to_vec()));
+        self
+    }
+
+    pub fn remove(mut self, path: &str) -> Self {
+        self.changes.push(Change::Remove(path.to_string()));
+        self
+    }
+
+    /// Moves the file at `from` to `to`, keeping its contents.
+    pub fn rename(mut self, from: &str, to: &str) -> Self {
+        self.changes
+            .push(Change::Rename(from.to_string(), to.to_string()));
+        self
+    }
+
+    /// Points the submodule at `path` to its commit `commit`.
+    pub fn pin(mut self, path: &str, commit: Oid) -> Self {
+        self.changes.push(Change::Pin(path.to_string(), commit));
+        self
+    }
+
+    /// Writes the commit and moves the branch to it.
+    pub fn create(self) -> Oid {
+        let repo = &self.fixture.repo;
+        let reference = match &self.branch {
+            Some(name) => format!("refs/heads/{}", name),
+            None => "HEAD".to_string(),
+        };
+
+        let mut parents: Vec<Commit> = self.fixture.tip(&reference).into_iter().collect();
+        let mut index = Index::new().unwrap();
+        if let Some(other) = self.merge {
+            let other = repo.find_commit(other).unwrap();
+            let mut merged = repo.merge_commits(&parents[0], &other, None).unwrap();
+            assert!(!merged.has_conflicts(), "the merge has conflicts");
+            let tree = repo.find_tree(merged.write_tree_to(repo).unwrap()).unwrap();
+            index.read_tree(&tree).unwrap();
+            parents.push(other);
+        } else if let Some(parent) = parents.first() {
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+
+        for change in &self.changes {
+            match change {
+                Change::Write(path, contents) => {
+                    let blob = repo.blob(contents).unwrap();
+                    add(&mut index, path, 0o100644, blob, contents.len());
+                }
+                Change::Remove(path) => index.remove_path(Path::new(path)).unwrap(),
+                Change::Rename(from, to) => {
+                    let entry = index.get_path(Path::new(from), 0).unwrap();
+                    let contents = repo.find_blob(entry.id).unwrap().content().to_vec();
+                    index.remove_path(Path::new(from)).unwrap();
+                    let blob = repo.blob(&contents).unwrap();
+                    add(&mut index, to, 0o100644, blob, contents.len());
+                }
+                Change::Pin(path, commit) => add(&mut index, path, 0o160000, *commit, 0),
+            }
+        }
+        let tree = repo.find_tree(index.write_tree_to(repo).unwrap()).unwrap();
+
+        let mut message = self.message.clone();
+        if !self.co_authors.is_empty() {
+            message.push('\n');
+            for co_author in &self.co_authors {
+                message.push_str(&format!(
+                    "\nCo-authored-by: {} <{}>",
+                    co_author.name, co_author.email
+                ));
+            }
+        }
+
+        let signature = Signature::new(self.author.name, self.author.email, &self.time).unwrap();
+        let parents: Vec<&Commit> = parents.iter().collect();
+        repo.commit(
+            Some(&reference),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+}
+
+fn add(index: &mut Index, path: &str, mode: u32, id: Oid, size: usize) {
+    let entry = IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: size as u32,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    };
+    index.add(&entry).unwrap();
+}
+
+fn parse_time(time: &str) -> Time {
+    let time = DateTime::parse_from_rfc3339(time).unwrap();
+    Time::new(time.timestamp(), time.offset().local_minus_utc() / 60)
+}
@@ -0,0 +1,792 @@
+mod support;
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use chrono::{Days, NaiveDate, TimeDelta, TimeZone, Utc};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use git_stats::{
+    analysis::{Analysis, AnalysisEvent, CommitRecord, FileChange, Progress},
+    languages::{LanguageStats, language_of},
+    repository::ShallowBoundary,
+    tui::{
+        app::{App, ExportTarget, View},
+        events::ScriptedEvents,
+        handle_event,
+        keymap::Keymap,
+        run_app, ui,
+    },
+    users::{UserInfo, UserStats},
+    watch::RefWatcher,
+};
+use git2::Oid;
+use insta::assert_snapshot;
+use ratatui::{Terminal, backend::TestBackend};
+use support::{ALICE, Fixture};
+use tempfile::TempDir;
+
+/// A user with `commits` commits, one every three days from `first_day`. The
+/// n-th commit adds `10 + n` lines to and removes `n` lines from `file`.
+fn user(id: usize, name: &str, commits: usize, first_day: NaiveDate, file: &str) -> UserInfo {
+    let email = format!("{}@example.com", name.to_lowercase());
+    let mut records: Vec<CommitRecord> = (0..commits)
+        .map(|n| {
+            let day = first_day + Days::new(3 * n as u64);
+            let time = Utc.from_utc_datetime(&day.and_hms_opt(12, 0, 0).unwrap());
+            CommitRecord {
+                oid: Oid::from_str(&format!("{:08x}{:032x}", id, n)).unwrap(),
+                author_name: name.to_string(),
+                author_email: email.clone(),
+                time,
+                summary: format!("Change {} of {}", n, name),
+                lines_added: 10 + n,
+                lines_removed: n,
+                files: vec![FileChange {
+                    path: file.to_string(),
+                    lines_added: 10 + n,
+                    lines_removed: n,
+                }],
+                excluded_files: Vec::new(),
+                renames: 0,
+                copies: 0,
+                source: None,
+            }
+        })
+        .collect();
+    records.reverse();
+
+    let total_lines_added = records.iter().map(|c| c.lines_added).sum();
+    let total_lines_removed = records.iter().map(|c| c.lines_removed).sum();
+    let mut line_changes_per_year = BTreeMap::new();
+    for record in &records {
+        *line_changes_per_year
+            .entry(record.time.format("%Y").to_string().parse().unwrap())
+            .or_default() += record.lines_added + record.lines_removed;
+    }
+
+    let mut user = UserInfo::new(name.to_string(), email);
+    user.stats = UserStats {
+        total_commits: commits as u32,
+        average_commits_per_day: 1.0,
+        average_commits_per_month: commits as f32 / 2.0,
+        average_commits_per_year: commits as f32,
+        total_lines_added,
+        total_lines_removed,
+        average_lines_added_per_day: total_lines_added as f32 / commits as f32,
+        average_lines_removed_per_day: total_lines_removed as f32 / commits as f32,
+        line_changes_per_year,
+        languages: BTreeMap::from([(
+            language_of(file),
+            LanguageStats {
+                lines_added: total_lines_added,
+                lines_removed: total_lines_removed,
+            },
+        )]),
+        first_commit: records.last().map(|c| c.time.date_naive()),
+        last_commit: records.first().map(|c| c.time.date_naive()),
+        commits: records,
+        ..UserStats::default()
+    };
+    user
+}
+
+fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn many_users() -> Vec<UserInfo> {
+    let names = [
+        "Alice", "Bob", "Carol", "Dan", "Erin", "Frank", "Grace", "Heidi", "Ivan", "Judy",
+        "Mallory", "Niaj",
+    ];
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            user(
+                i,
+                name,
+                11 + 3 * i,
+                day(2023, 1 + i as u32 % 6, 1),
+                &format!("src/module_{}.rs", i % 4),
+            )
+        })
+        .collect()
+}
+
+/// The text of the terminal, one line per row without trailing spaces.
+fn render(app: &mut App, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| ui::render(f, app)).unwrap();
+    buffer_text(&terminal)
+}
+
+fn buffer_text(terminal: &Terminal<TestBackend>) -> String {
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            let line: String = (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Runs `app` with the scripted `events`, and returns the text of the
+/// terminal once it quit.
+fn run(app: &mut App, events: ScriptedEvents, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    let mut events = events;
+    run_app(&mut terminal, app, &mut events, Duration::from_secs(60)).unwrap();
+    assert!(
+        events.is_empty(),
+        "the app quit before the end of the script"
+    );
+    buffer_text(&terminal)
+}
+
+#[test]
+fn renders_empty_user_list() {
+    let mut app = App::new(Vec::new());
+    assert_snapshot!("empty", render(&mut app, 80, 20));
+}
+
+#[test]
+fn renders_one_user() {
+    let mut alice = user(0, "Alice", 12, day(2023, 3, 1), "src/main.rs");
+    alice.stats.languages.insert(
+        "Markdown",
+        LanguageStats {
+            lines_added: 40,
+            lines_removed: 8,
+        },
+    );
+    let mut app = App::new(vec![alice]);
+    assert_snapshot!("one_user", render(&mut app, 100, 30));
+}
+
+#[test]
+fn renders_many_users() {
+    let mut app = App::new(many_users());
+    assert_snapshot!("many_users", render(&mut app, 120, 30));
+}
+
+#[test]
+fn renders_narrow_terminal() {
+    let mut app = App::new(many_users());
+    assert_snapshot!("narrow", render(&mut app, 40, 16));
+}
+
+#[test]
+fn renders_shallow_clone_summary() {
+    let mut app = App::new(many_users());
+    app.partial = Some(ShallowBoundary {
+        commits: vec![Oid::zero()],
+        date: Utc.with_ymd_and_hms(2023, 3, 1, 12, 0, 0).unwrap(),
+    });
+    app.view = View::Summary;
+    assert_snapshot!("shallow_summary", render(&mut app, 100, 24));
+}
+
+#[test]
+fn leaves_out_users_with_few_commits() {
+    let app = App::new(vec![
+        user(0, "Alice", 11, day(2023, 1, 1), "a.rs"),
+        user(1, "Bob", 10, day(2023, 1, 1), "b.rs"),
+    ]);
+    let names: Vec<&str> = app
+        .visible_users
+        .iter()
+        .map(|&i| app.users()[i].name.as_str())
+        .collect();
+    assert_eq!(names, ["Alice"]);
+}
+
+#[test]
+fn scripted_keys_move_the_selection() {
+    let mut app = App::new(many_users());
+    run(&mut app, ScriptedEvents::keys("jjjkq"), 120, 30);
+    // Sorted by commits, most first, starting on the first row
+    assert_eq!(app.selected_user().map(|u| u.name.as_str()), Some("Judy"));
+    assert!(app.should_quit);
+}
+
+#[test]
+fn scripted_keys_jump_to_the_last_user() {
+    let mut app = App::new(many_users());
+    run(&mut app, ScriptedEvents::keys("Gq"), 120, 30);
+    assert_eq!(app.selected_user().map(|u| u.name.as_str()), Some("Alice"));
+}
+
+#[test]
+fn scripted_search_filters_users() {
+    let mut app = App::new(many_users());
+    let events = ScriptedEvents::keys("/^[ab]")
+        .then_key(KeyCode::Enter)
+        .then_key(KeyCode::Char('q'));
+    let screen = run(&mut app, events, 100, 24);
+    let names: Vec<&str> = app
+        .visible_users
+        .iter()
+        .map(|&i| app.users()[i].name.as_str())
+        .collect();
+    assert_eq!(names, ["Bob", "Alice"]);
+    assert_snapshot!("search", screen);
+}
+
+#[test]
+fn search_uses_the_keys_of_the_keymap() {
+    let overrides: HashMap<String, Vec<String>> = [("open", "ctrl-o"), ("back", "ctrl-g")]
+        .into_iter()
+        .map(|(action, key)| (action.to_string(), vec![key.to_string()]))
+        .collect();
+    let keymap = Keymap::with_overrides(&overrides).unwrap();
+    let mut app = App::new(many_users()).with_keymap(keymap);
+    let key = |code, modifiers| Event::Key(KeyEvent::new(code, modifiers));
+
+    app.start_search();
+    handle_event(&mut app, key(KeyCode::Char('b'), KeyModifiers::NONE));
+    // Enter isn't bound to anything anymore
+    handle_event(&mut app, key(KeyCode::Enter, KeyModifiers::NONE));
+    assert!(app.search_mode);
+    handle_event(&mut app, key(KeyCode::Char('o'), KeyModifiers::CONTROL));
+    assert!(!app.search_mode);
+    assert_eq!(app.filter, "b");
+
+    app.start_search();
+    handle_event(&mut app, key(KeyCode::Char('g'), KeyModifiers::CONTROL));
+    assert!(!app.search_mode);
+    assert!(app.filter.is_empty());
+}
+
+#[test]
+fn scripted_keys_switch_views() {
+    let mut app = App::new(many_users());
+    let screen = run(&mut app, ScriptedEvents::keys("3q"), 100, 24);
+    assert_eq!(app.view, View::Files);
+    assert_snapshot!("files_view", screen);
+}
+
+#[test]
+fn scripted_help_popup() {
+    let mut app = App::new(many_users());
+    // Quitting from the help popup works too
+    let screen = run(&mut app, ScriptedEvents::keys("?jq"), 100, 30);
+    assert!(app.show_help);
+    assert_eq!(app.selected_user().map(|u| u.name.as_str()), Some("Niaj"));
+    assert_snapshot!("help", screen);
+}
+
+#[test]
+fn script_that_never_quits_fails() {
+    let mut app = App::new(many_users());
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    let mut events = ScriptedEvents::keys("jj");
+    let result = run_app(
+        &mut terminal,
+        &mut app,
+        &mut events,
+        Duration::from_secs(60),
+    );
+    assert!(result.is_err());
+}
+
+/// Exports `target` from `app` to `path` like typing the path in the prompt,
+/// and returns the status message.
+fn export(app: &mut App, target: ExportTarget, path: &std::path::Path) -> (String, bool) {
+    app.start_export(target);
+    app.export_prompt.as_mut().unwrap().path = path.display().to_string();
+    app.finish_export();
+    let status = app.status.clone().unwrap();
+    (status.text, status.is_error)
+}
+
+/// Names of the users of a JSON export.
+fn exported_names(path: &std::path::Path) -> Vec<String> {
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+    let users = match json {
+        serde_json::Value::Array(users) => users,
+        user => vec![user],
+    };
+    users
+        .iter()
+        .map(|u| u["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn exports_the_selected_user() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("user.json");
+    let mut app = App::new(many_users());
+    app.next();
+    let selected = app.selected_user().unwrap().name.clone();
+
+    let (status, is_error) = export(&mut app, ExportTarget::User, &path);
+    assert!(!is_error, "{status}");
+    assert_eq!(status, format!("Exported user stats to {}", path.display()));
+    assert_eq!(exported_names(&path), [selected]);
+    assert!(app.export_prompt.is_none());
+}
+
+#[test]
+fn export_prompt_stays_open_on_an_invalid_extension() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("user.txt").display().to_string();
+    let mut app = App::new(many_users());
+    let key = |code| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+
+    app.start_export(ExportTarget::User);
+    while !app.export_prompt.as_ref().unwrap().path.is_empty() {
+        handle_event(&mut app, key(KeyCode::Backspace));
+    }
+    for c in path.chars() {
+        handle_event(&mut app, key(KeyCode::Char(c)));
+    }
+    handle_event(&mut app, key(KeyCode::Enter));
+
+    let status = app.status.clone().unwrap();
+    assert!(status.is_error);
+    assert_eq!(
+        status.text,
+        "Export failed: Unknown export format '.txt', use .md, .json or .csv"
+    );
+    // The path can be fixed rather than typed again
+    assert_eq!(app.export_prompt.as_ref().unwrap().path, path);
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn exports_the_compared_users_in_the_order_they_were_marked() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("comparison.json");
+    let mut app = App::new(many_users());
+    app.next();
+    app.next();
+    app.toggle_mark();
+    let first = app.selected_user().unwrap().name.clone();
+    app.previous();
+    app.toggle_mark();
+    let second = app.selected_user().unwrap().name.clone();
+
+    // Only the comparison view exports the comparison
+    app.start_export(ExportTarget::Comparison);
+    assert!(app.export_prompt.is_none());
+    app.open_comparison();
+
+    let (status, is_error) = export(&mut app, ExportTarget::Comparison, &path);
+    assert!(!is_error, "{status}");
+    assert_eq!(exported_names(&path), [first, second]);
+}
+
+#[test]
+fn export_never_overwrites_a_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("user.md");
+    std::fs::write(&path, "notes").unwrap();
+    let mut app = App::new(many_users());
+
+    let (status, is_error) = export(&mut app, ExportTarget::User, &path);
+    assert!(is_error);
+    assert_eq!(
+        status,
+        format!("{} already exists, enter another path", path.display())
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+    // The prompt stays open to enter another path
+    assert!(app.export_prompt.is_some());
+}
+
+#[test]
+fn renders_popups_on_wide_terminals() {
+    let mut app = App::new(many_users());
+    app.toggle_help();
+    let screen = render(&mut app, 1200, 40);
+    assert!(screen.contains("Keys (?: close)"));
+}
+
+#[test]
+fn hints_show_the_keys_of_the_keymap() {
+    let overrides: HashMap<String, Vec<String>> = [
+        ("granularity", "p"),
+        ("compare", "v"),
+        ("open", "ctrl-o"),
+        ("back", "ctrl-g"),
+    ]
+    .into_iter()
+    .map(|(action, key)| (action.to_string(), vec![key.to_string()]))
+    .collect();
+    let keymap = Keymap::with_overrides(&overrides).unwrap();
+    let mut default_app = App::new(many_users());
+    let mut app = App::new(many_users()).with_keymap(keymap);
+
+    // The activity chart of the selected user
+    assert!(render(&mut default_app, 160, 40).contains("(t: period, m: metric)"));
+    assert!(render(&mut app, 160, 40).contains("(p: period, m: metric)"));
+
+    app.toggle_mark();
+    app.next();
+    app.toggle_mark();
+    assert!(render(&mut app, 120, 30).contains("[2 marked, v: compare]"));
+    app.open_comparison();
+    assert!(render(&mut app, 120, 30).contains("Comparison (ctrl-g: back)"));
+
+    app.start_export(ExportTarget::Comparison);
+    assert!(render(&mut app, 120, 30).contains("ctrl-o: write, ctrl-g: cancel"));
+    // The remapped keys close the prompt, while characters are typed
+    let key = |code, modifiers| Event::Key(KeyEvent::new(code, modifiers));
+    handle_event(&mut app, key(KeyCode::Char('p'), KeyModifiers::NONE));
+    assert!(app.export_prompt.as_ref().unwrap().path.ends_with(".mdp"));
+    handle_event(&mut app, key(KeyCode::Char('g'), KeyModifiers::CONTROL));
+    assert!(app.export_prompt.is_none());
+}
+
+#[test]
+fn commit_files_list_the_excluded_files() {
+    let mut alice = user(0, "Alice", 11, day(2023, 1, 1), "src/lib.rs");
+    let commit = &mut alice.stats.commits[0];
+    commit.excluded_files.push(FileChange {
+        path: "Cargo.lock".to_string(),
+        lines_added: 120,
+        lines_removed: 80,
+    });
+    let mut app = App::new(vec![alice]);
+    app.open_commits();
+    app.open_commit_files();
+
+    let screen = render(&mut app, 120, 20);
+    assert!(screen.contains("1 files, +20 -10, 1 excluded)"), "{screen}");
+    let excluded = screen
+        .lines()
+        .find(|line| line.contains("Cargo.lock (excluded)"))
+        .unwrap();
+    assert!(excluded.contains("+120") && excluded.contains("-80"));
+    // The excluded files can be scrolled to like the others
+    app.next_commit();
+    assert_eq!(app.commit_view.as_ref().unwrap().file_scroll, 1);
+}
+
+/// A repository with `commits` commits by Alice, a minute apart, each adding a
+/// line to `log.txt`.
+fn history(commits: usize) -> Fixture {
+    let fixture = Fixture::new();
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap();
+    let mut log = String::new();
+    for n in 0..commits {
+        log.push_str(&format!("line {}\n", n));
+        let time = start + TimeDelta::minutes(n as i64);
+        fixture
+            .commit(ALICE, &time.to_rfc3339())
+            .write("log.txt", &log)
+            .create();
+    }
+    fixture
+}
+
+/// Ticks `app` until `done` holds, failing after a few seconds.
+fn tick_until(app: &mut App, done: impl Fn(&App) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !done(app) {
+        assert!(Instant::now() < deadline, "timed out");
+        std::thread::sleep(Duration::from_millis(10));
+        app.on_tick();
+    }
+}
+
+#[test]
+fn analysis_reports_progress_after_each_commit() {
+    let fixture = history(4);
+    let tip = fixture.repo.head().unwrap().target().unwrap();
+    let events: Vec<AnalysisEvent> = Analysis::spawn(fixture.path(), "HEAD").collect();
+
+    assert_eq!(
+        events[0],
+        AnalysisEvent::Started {
+            total_commits: 4,
+            tip,
+            partial: None
+        }
+    );
+    // Every commit moves the progress by 25%, right after its record
+    for (n, pair) in events[1..9].chunks(2).enumerate() {
+        assert!(matches!(pair[0], AnalysisEvent::Commit(_)));
+        assert_eq!(
+            pair[1],
+            AnalysisEvent::Progress(Progress {
+                processed: n + 1,
+                total: 4
+            })
+        );
+    }
+    assert_eq!(events[9..], [AnalysisEvent::Finished]);
+}
+
+#[test]
+fn analysis_stops_when_cancelled_mid_walk() {
+    let fixture = history(500);
+    let mut analysis = Analysis::spawn(fixture.path(), "HEAD");
+    assert!(matches!(
+        analysis.next(),
+        Some(AnalysisEvent::Started {
+            total_commits: 500,
+            ..
+        })
+    ));
+    assert!(matches!(analysis.next(), Some(AnalysisEvent::Commit(_))));
+
+    analysis.cancel();
+    let rest: Vec<AnalysisEvent> = analysis.collect();
+    assert_eq!(rest.last(), Some(&AnalysisEvent::Cancelled));
+    let commits = rest
+        .iter()
+        .filter(|e| matches!(e, AnalysisEvent::Commit(_)))
+        .count();
+    assert!(commits < 499, "the walk went on after the cancel");
+}
+
+#[test]
+fn app_loads_the_stats_in_the_background() {
+    let fixture = history(12);
+    let mut app = App::with_analysis(Analysis::spawn(fixture.path(), "HEAD"));
+    assert!(app.is_loading());
+    assert!(render(&mut app, 80, 10).contains("Loading repository stats..."));
+
+    tick_until(&mut app, |app| !app.is_loading());
+    assert_eq!(app.visible_users.len(), 1);
+    let alice = app.selected_user().unwrap();
+    assert_eq!(
+        (alice.name.as_str(), alice.stats.total_commits),
+        ("Alice", 12)
+    );
+    assert_eq!(app.repo_stats().total_commits, 12);
+}
+
+/// An app showing the stats of `fixture`, reloaded whenever its refs move.
+fn watching_app(fixture: &Fixture) -> App {
+    watching_app_of(fixture, "HEAD")
+}
+
+/// Like [`watching_app`], for the commits of `revspec`.
+fn watching_app_of(fixture: &Fixture, revspec: &str) -> App {
+    let mut app = App::with_analysis(Analysis::spawn(fixture.path(), revspec))
+        .with_ref_watcher(RefWatcher::new(&fixture.repo).unwrap());
+    tick_until(&mut app, |app| !app.is_loading());
+    app
+}
+
+fn alice_commits(app: &App) -> u32 {
+    app.selected_user().map_or(0, |u| u.stats.total_commits)
+}
+
+#[test]
+fn watcher_analyzes_the_new_commits_of_a_fast_forward() {
+    let fixture = history(12);
+    let mut app = watching_app(&fixture);
+    let alice = app.selected_user().unwrap().id;
+    assert_eq!(alice_commits(&app), 12);
+
+    let new = fixture
+        .commit(ALICE, "2023-02-01T12:00:00Z")
+        .write("notes.txt", "1\n2\n")
+        .create();
+    tick_until(&mut app, |app| alice_commits(app) == 13);
+
+    // Only the new commit was added to the stats shown
+    let user = app.selected_user().unwrap();
+    assert_eq!(user.id, alice);
+    assert_eq!(user.stats.commits[0].oid, new);
+    assert_eq!(user.stats.total_lines_added, 14);
+    assert_eq!(app.repo_stats().total_commits, 13);
+}
+
+#[test]
+fn watcher_reloads_the_stats_of_a_range() {
+    let fixture = history(13);
+    let first = fixture.repo.revparse_single("main~12").unwrap().id();
+    let mut app = watching_app_of(&fixture, &format!("{}..main", first));
+    assert_eq!(alice_commits(&app), 12);
+
+    fixture
+        .commit(ALICE, "2023-02-01T12:00:00Z")
+        .write("notes.txt", "1\n")
+        .create();
+    tick_until(&mut app, |app| alice_commits(app) == 13);
+    assert_eq!(app.status, None);
+}
+
+#[test]
+fn watcher_analyzes_everything_again_after_an_amend() {
+    let fixture = history(12);
+    let mut app = watching_app(&fixture);
+    let alice = app.selected_user().unwrap().id;
+    let tip = fixture.repo.head().unwrap().peel_to_commit().unwrap();
+
+    let amended = tip
+        .amend(Some("HEAD"), None, None, None, Some("Amended"), None)
+        .unwrap();
+    tick_until(&mut app, |app| {
+        !app.is_loading() && app.selected_user().is_some_and(|u| u.id != alice)
+    });
+
+    // The amended commit replaces the original one instead of adding to it
+    let user = app.selected_user().unwrap();
+    assert_eq!(user.stats.total_commits, 12);
+    let oids: Vec<Oid> = user.stats.commits.iter().map(|c| c.oid).collect();
+    assert!(oids.contains(&amended));
+    assert!(!oids.contains(&tip.id()));
+    assert_eq!(app.repo_stats().total_commits, 12);
+}
+
+fn compared_names(app: &App) -> Vec<&str> {
+    app.compared_users()
+        .iter()
+        .map(|u| u.name.as_str())
+        .collect()
+}
+
+#[test]
+fn scripted_comparison_of_the_marked_users() {
+    let mut app = App::new(many_users());
+    let screen = run(&mut app, ScriptedEvents::keys(" jj cq"), 120, 30);
+    assert!(app.comparing);
+    let expected: Vec<&str> = [0, 2]
+        .iter()
+        .map(|&row| app.users()[app.visible_users[row]].name.as_str())
+        .collect();
+    assert_eq!(compared_names(&app), expected);
+    assert_snapshot!("comparison", screen);
+}
+
+#[test]
+fn comparison_needs_two_marked_users() {
+    let mut app = App::new(many_users());
+    run(&mut app, ScriptedEvents::keys(" cq"), 120, 30);
+    assert!(!app.comparing);
+
+    // Marking again unmarks
+    let mut app = App::new(many_users());
+    run(&mut app, ScriptedEvents::keys(" j  kcq"), 120, 30);
+    assert_eq!(compared_names(&app), ["Niaj"]);
+    assert!(!app.comparing);
+}
+
+#[test]
+fn comparison_closes_back_to_the_users() {
+    let mut app = App::new(many_users());
+    let events = ScriptedEvents::keys(" j c")
+        .then_key(KeyCode::Esc)
+        .then_key(KeyCode::Char('j'))
+        .then_key(KeyCode::Char('q'));
+    run(&mut app, events, 120, 30);
+    assert!(!app.comparing);
+    // Esc went back to the list instead of quitting, where j moves on
+    assert_eq!(app.selected_visible_index(), Some(2));
+    // The marks stay for the next comparison
+    assert_eq!(compared_names(&app).len(), 2);
+}
+
+#[test]
+fn views_can_be_switched_while_comparing() {
+    let mut app = App::new(many_users());
+    let events = ScriptedEvents::keys(" j c3")
+        .then_key(KeyCode::Tab)
+        .then_key(KeyCode::Char('1'))
+        .then_key(KeyCode::Char('j'))
+        .then_key(KeyCode::Char('q'));
+    run(&mut app, events, 120, 30);
+    // Back on the comparison, where j doesn't move the hidden selection
+    assert_eq!(app.view, View::Authors);
+    assert!(app.comparing);
+    assert_eq!(app.selected_visible_index(), Some(1));
+
+    let key = Event::Key(KeyEvent::new(KeyCode::Char('2'), KeyModifiers::NONE));
+    handle_event(&mut app, key);
+    assert_eq!(app.view, View::Timeline);
+}
+
+/// Draws `app` so it knows where everything is, then sends it a mouse event
+/// at `column`, `row`.
+fn mouse(app: &mut App, kind: MouseEventKind, column: u16, row: u16) {
+    render(app, 120, 30);
+    let event = MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    };
+    handle_event(app, Event::Mouse(event));
+}
+
+const CLICK: MouseEventKind = MouseEventKind::Down(MouseButton::Left);
+
+#[test]
+fn clicking_a_tab_switches_views() {
+    let mut app = App::new(many_users());
+    // " 1 Authors │ 2 Timeline │ 3 Files │ 4 Summary"
+    mouse(&mut app, CLICK, 30, 0);
+    assert_eq!(app.view, View::Files);
+    mouse(&mut app, CLICK, 3, 0);
+    assert_eq!(app.view, View::Authors);
+}
+
+#[test]
+fn clicking_a_row_selects_it() {
+    let mut app = App::new(many_users());
+    // Below the tabs, the border and the header of the users table
+    mouse(&mut app, CLICK, 5, 5);
+    assert_eq!(app.selected_visible_index(), Some(2));
+    // Clicking the header selects nothing
+    mouse(&mut app, CLICK, 5, 2);
+    assert_eq!(app.selected_visible_index(), Some(2));
+
+    app.open_commits();
+    // The commits table is right of the users one, without a header
+    mouse(&mut app, CLICK, 60, 4);
+    assert_eq!(app.commit_view.as_ref().unwrap().selected, 2);
+
+    app.close_commit_view();
+    app.view = View::Files;
+    mouse(&mut app, CLICK, 5, 5);
+    assert_eq!(app.selected_file_index, 2);
+}
+
+#[test]
+fn scrolling_moves_the_selection_under_the_cursor() {
+    let mut app = App::new(many_users());
+    mouse(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    mouse(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    mouse(&mut app, MouseEventKind::ScrollUp, 5, 10);
+    assert_eq!(app.selected_visible_index(), Some(1));
+
+    app.open_commits();
+    mouse(&mut app, MouseEventKind::ScrollDown, 60, 10);
+    mouse(&mut app, MouseEventKind::ScrollDown, 60, 10);
+    assert_eq!(app.commit_view.as_ref().unwrap().selected, 2);
+    // The users don't move while scrolling the commits
+    assert_eq!(app.selected_visible_index(), Some(1));
+
+    // Scrolling outside of any list does nothing
+    app.close_commit_view();
+    app.view = View::Summary;
+    mouse(&mut app, MouseEventKind::ScrollDown, 5, 10);
+    assert_eq!(app.selected_visible_index(), Some(1));
+}
+
+#[test]
+fn hovering_a_bar_shows_its_value() {
+    let mut app = App::new(many_users());
+    app.view = View::Timeline;
+    mouse(&mut app, MouseEventKind::Moved, 10, 10);
+    let hovered = app.hovered_bar.clone().unwrap();
+    assert_eq!(hovered.chart, "Commits per Month");
+    assert!(render(&mut app, 120, 30).contains(&format!("{}: {}", hovered.label, hovered.value)));
+
+    // Moving out of the charts hides it
+    mouse(&mut app, MouseEventKind::Moved, 10, 0);
+    assert_eq!(app.hovered_bar, None);
+}
@@ -0,0 +1,400 @@
+mod support;
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate};
+use git_stats::{
+    analysis::{CommitRecord, FileChange},
+    languages::{LanguageStats, OTHER},
+    users::{StatsAccumulator, UserInfo, UserStats, get_users, update_user_stats},
+};
+use git2::Oid;
+use support::{ALICE, Author, BOB, CAROL, Fixture};
+
+/// Stats of every author of `fixture`, by name.
+fn stats(fixture: &Fixture) -> BTreeMap<String, UserStats> {
+    let mut users = get_users(&fixture.repo).unwrap();
+    update_user_stats(&mut users, &fixture.repo, "main").unwrap();
+    users.into_iter().map(|u| (u.name, u.stats)).collect()
+}
+
+fn record(
+    oid: Oid,
+    author: Author,
+    time: &str,
+    summary: &str,
+    files: &[(&str, usize, usize)],
+) -> CommitRecord {
+    let files: Vec<FileChange> = files
+        .iter()
+        .map(|&(path, lines_added, lines_removed)| FileChange {
+            path: path.to_string(),
+            lines_added,
+            lines_removed,
+        })
+        .collect();
+    CommitRecord {
+        oid,
+        author_name: author.name.to_string(),
+        author_email: author.email.to_string(),
+        time: DateTime::parse_from_rfc3339(time).unwrap().to_utc(),
+        summary: summary.to_string(),
+        lines_added: files.iter().map(|f| f.lines_added).sum(),
+        lines_removed: files.iter().map(|f| f.lines_removed).sum(),
+        files,
+        excluded_files: Vec::new(),
+        renames: 0,
+        copies: 0,
+        source: None,
+    }
+}
+
+fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn finds_every_author() {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-15T10:00:00Z")
+        .write("a.txt", "1\n")
+        .create();
+    fixture
+        .commit(BOB, "2023-01-16T10:00:00Z")
+        .write("a.txt", "1\n2\n")
+        .create();
+    fixture
+        .commit(ALICE, "2023-01-17T10:00:00Z")
+        .write("a.txt", "1\n2\n3\n")
+        .create();
+
+    let mut users = get_users(&fixture.repo).unwrap();
+    users.sort_by(|a, b| a.name.cmp(&b.name));
+    let authors: Vec<(&str, &str)> = users
+        .iter()
+        .map(|u| (u.name.as_str(), u.email.as_str()))
+        .collect();
+    assert_eq!(
+        authors,
+        [("Alice", "alice@example.com"), ("Bob", "bob@example.com")]
+    );
+    assert!(users.iter().all(|u| u.has_not_stats()));
+}
+
+#[test]
+fn authors_are_known_by_email_or_name() {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-01-15T10:00:00Z")
+        .write("a.txt", "1\n")
+        .create();
+    let renamed = Author {
+        name: "Alice Liddell",
+        email: ALICE.email,
+    };
+    fixture
+        .commit(renamed, "2023-01-16T10:00:00Z")
+        .write("a.txt", "1\n2\n")
+        .create();
+
+    let mut users = get_users(&fixture.repo).unwrap();
+    assert_eq!(users.len(), 1);
+    update_user_stats(&mut users, &fixture.repo, "main").unwrap();
+    assert_eq!(users[0].stats.total_commits, 2);
+}
+
+#[test]
+fn stats_of_a_single_author() {
+    let fixture = Fixture::new();
+    let first = fixture
+        .commit(ALICE, "2023-01-15T10:00:00Z")
+        .message("Add a")
+        .write("a.txt", "1\n2\n3\n")
+        .create();
+    let second = fixture
+        .commit(ALICE, "2023-01-15T18:00:00Z")
+        .message("Change a, add b")
+        .write("a.txt", "1\ntwo\n3\n")
+        .write("b.txt", "x\ny\n")
+        .create();
+    // Same day of the same month as the first commits, a year later
+    let third = fixture
+        .commit(ALICE, "2024-01-15T09:00:00+01:00")
+        .message("Remove b")
+        .remove("b.txt")
+        .create();
+    // Still January 31st in UTC
+    let fourth = fixture
+        .commit(ALICE, "2024-02-01T00:30:00+02:00")
+        .message("Extend a")
+        .write("a.txt", "1\ntwo\n3\n4\n")
+        .create();
+
+    let stats = stats(&fixture);
+    assert_eq!(
+        stats["Alice"],
+        UserStats {
+            total_commits: 4,
+            average_commits_per_day: 4.0 / 3.0,
+            average_commits_per_month: 2.0,
+            average_commits_per_year: 2.0,
+            total_lines_added: 7,
+            total_lines_removed: 3,
+            average_lines_added_per_day: 7.0 / 3.0,
+            average_lines_removed_per_day: 1.0,
+            average_lines_added_per_month: 3.5,
+            average_lines_removed_per_month: 1.5,
+            average_lines_added_per_year: 3.5,
+            average_lines_removed_per_year: 1.5,
+            excluded_lines_added: 0,
+            excluded_lines_removed: 0,
+            renames: 0,
+            copies: 0,
+            line_changes_per_year: BTreeMap::from([(2023, 7), (2024, 3)]),
+            line_changes_per_month: BTreeMap::from([(day(2023, 1, 1), 7), (day(2024, 1, 1), 3)]),
+            line_changes_per_day: BTreeMap::from([
+                (day(2023, 1, 15), 7),
+                (day(2024, 1, 15), 2),
+                (day(2024, 1, 31), 1),
+            ]),
+            first_commit: Some(day(2023, 1, 15)),
+            last_commit: Some(day(2024, 1, 31)),
+            languages: BTreeMap::from([(
+                OTHER,
+                LanguageStats {
+                    lines_added: 7,
+                    lines_removed: 3,
+                },
+            )]),
+            commits: vec![
+                record(
+                    fourth,
+                    ALICE,
+                    "2024-02-01T00:30:00+02:00",
+                    "Extend a",
+                    &[("a.txt", 1, 0)],
+                ),
+                record(
+                    third,
+                    ALICE,
+                    "2024-01-15T09:00:00+01:00",
+                    "Remove b",
+                    &[("b.txt", 0, 2)],
+                ),
+                record(
+                    second,
+                    ALICE,
+                    "2023-01-15T18:00:00Z",
+                    "Change a, add b",
+                    &[("a.txt", 1, 1), ("b.txt", 2, 0)],
+                ),
+                record(
+                    first,
+                    ALICE,
+                    "2023-01-15T10:00:00Z",
+                    "Add a",
+                    &[("a.txt", 3, 0)],
+                ),
+            ],
+        }
+    );
+}
+
+#[test]
+fn stats_are_split_by_author() {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-03-01T12:00:00Z")
+        .write("a.txt", "1\n2\n")
+        .create();
+    fixture
+        .commit(BOB, "2023-03-02T23:30:00-07:00")
+        .write("b.txt", "1\n2\n3\n4\n5\n")
+        .create();
+    fixture
+        .commit(ALICE, "2023-04-10T12:00:00Z")
+        .write("a.txt", "1\n")
+        .create();
+
+    let stats = stats(&fixture);
+    let alice = &stats["Alice"];
+    assert_eq!(alice.total_commits, 2);
+    assert_eq!((alice.total_lines_added, alice.total_lines_removed), (2, 1));
+    assert_eq!(alice.average_commits_per_month, 1.0);
+    assert_eq!(
+        alice.line_changes_per_month,
+        BTreeMap::from([(day(2023, 3, 1), 2), (day(2023, 4, 1), 1)])
+    );
+
+    let bob = &stats["Bob"];
+    assert_eq!(bob.total_commits, 1);
+    assert_eq!((bob.total_lines_added, bob.total_lines_removed), (5, 0));
+    // Days are the ones of UTC
+    assert_eq!(bob.first_commit, Some(day(2023, 3, 3)));
+    assert_eq!(
+        bob.line_changes_per_day,
+        BTreeMap::from([(day(2023, 3, 3), 5)])
+    );
+}
+
+#[test]
+fn merges_count_as_commits_without_lines() {
+    let fixture = Fixture::new();
+    let base = fixture
+        .commit(ALICE, "2023-05-01T12:00:00Z")
+        .write("a.txt", "1\n")
+        .create();
+    fixture.branch("feature", base);
+    let feature = fixture
+        .commit(BOB, "2023-05-02T12:00:00Z")
+        .on("feature")
+        .write("b.txt", "1\n2\n")
+        .create();
+    fixture
+        .commit(ALICE, "2023-05-03T12:00:00Z")
+        .write("a.txt", "1\n2\n3\n")
+        .create();
+    let merge = fixture
+        .commit(CAROL, "2023-05-04T12:00:00Z")
+        .message("Merge feature")
+        .merge(feature)
+        .create();
+
+    let stats = stats(&fixture);
+    assert_eq!(stats["Alice"].total_commits, 2);
+    assert_eq!(stats["Alice"].total_lines_added, 3);
+    assert_eq!(stats["Bob"].total_commits, 1);
+    assert_eq!(stats["Bob"].total_lines_added, 2);
+    assert_eq!(
+        stats["Carol"].commits,
+        [record(
+            merge,
+            CAROL,
+            "2023-05-04T12:00:00Z",
+            "Merge feature",
+            &[]
+        )]
+    );
+    assert_eq!(
+        stats["Carol"].line_changes_per_day,
+        BTreeMap::from([(day(2023, 5, 4), 0)])
+    );
+}
+
+#[test]
+fn renames_only_count_the_changed_lines() {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-06-01T12:00:00Z")
+        .write("old.txt", "1\n2\n3\n4\n")
+        .create();
+    let rename = fixture
+        .commit(BOB, "2023-06-02T12:00:00Z")
+        .message("Rename")
+        .rename("old.txt", "new.txt")
+        .write("new.txt", "1\n2\n3\nfour\n")
+        .create();
+
+    let stats = stats(&fixture);
+    let mut expected = record(
+        rename,
+        BOB,
+        "2023-06-02T12:00:00Z",
+        "Rename",
+        &[("new.txt", 1, 1)],
+    );
+    expected.renames = 1;
+    assert_eq!(stats["Bob"].commits, [expected]);
+    assert_eq!((stats["Bob"].renames, stats["Alice"].renames), (1, 0));
+}
+
+#[test]
+fn binary_files_are_excluded() {
+    let fixture = Fixture::new();
+    let commit = fixture
+        .commit(ALICE, "2023-07-01T12:00:00Z")
+        .message("Add logo")
+        .write_bytes(
+            "logo.png",
+            &[0x89, b'P', b'N', b'G', 0, 0, 0, 13, b'\n', 0xff],
+        )
+        .write("README", "Logo\n")
+        .create();
+
+    let stats = stats(&fixture);
+    let mut expected = record(
+        commit,
+        ALICE,
+        "2023-07-01T12:00:00Z",
+        "Add logo",
+        &[("README", 1, 0)],
+    );
+    expected.excluded_files = vec![FileChange {
+        path: "logo.png".to_string(),
+        lines_added: 0,
+        lines_removed: 0,
+    }];
+    assert_eq!(stats["Alice"].commits, [expected]);
+}
+
+#[test]
+fn co_authors_are_not_credited() {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-08-01T12:00:00Z")
+        .message("Pair on a")
+        .co_author(BOB)
+        .write("a.txt", "1\n2\n")
+        .create();
+
+    let stats = stats(&fixture);
+    assert_eq!(stats.keys().collect::<Vec<_>>(), ["Alice"]);
+    assert_eq!(stats["Alice"].total_lines_added, 2);
+}
+
+#[test]
+fn only_updates_the_given_users() {
+    let fixture = Fixture::new();
+    fixture
+        .commit(ALICE, "2023-09-01T12:00:00Z")
+        .write("a.txt", "1\n")
+        .create();
+    fixture
+        .commit(BOB, "2023-09-02T12:00:00Z")
+        .write("b.txt", "1\n")
+        .create();
+
+    let mut users = vec![UserInfo::new(BOB.name.to_string(), BOB.email.to_string())];
+    update_user_stats(&mut users, &fixture.repo, "main").unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].stats.total_commits, 1);
+    assert_eq!(users[0].stats.first_commit, Some(day(2023, 9, 2)));
+}
+
+#[test]
+fn accumulator_matches_update_user_stats() {
+    let fixture = Fixture::new();
+    for (i, author) in [ALICE, BOB, ALICE, CAROL, ALICE].into_iter().enumerate() {
+        let contents = "line\n".repeat(i + 1);
+        fixture
+            .commit(author, &format!("2023-{:02}-28T23:00:00-02:00", i + 1))
+            .write(&format!("{}.txt", author.name), &contents)
+            .create();
+    }
+
+    let mut accumulator = StatsAccumulator::new();
+    let mut revwalk = fixture.repo.revwalk().unwrap();
+    revwalk.push_head().unwrap();
+    for oid in revwalk {
+        let commit = fixture.repo.find_commit(oid.unwrap()).unwrap();
+        accumulator.add(CommitRecord::from_commit(&fixture.repo, &commit).unwrap());
+    }
+    let accumulated: BTreeMap<String, UserStats> = accumulator
+        .users()
+        .iter()
+        .map(|u| (u.name.clone(), u.stats.clone()))
+        .collect();
+
+    assert_eq!(accumulated, stats(&fixture));
+}